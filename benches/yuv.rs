@@ -0,0 +1,80 @@
+//! Compares the CPU NV12 to RGBA converter against the original per-pixel implementation.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+#[allow(dead_code)]
+#[path = "../src/yuv.rs"]
+mod yuv;
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+/// The converter as it was before the rewrite; nearest-neighbour integer downscale only.
+fn yuv_to_rgba_legacy(yuv: &[u8], width: u32, height: u32, downscale: u32) -> Vec<u8> {
+    let uv_start = width * height;
+    let mut rgba = vec![];
+
+    for y in 0..height / downscale {
+        for x in 0..width / downscale {
+            let x_src = x * downscale;
+            let y_src = y * downscale;
+
+            let y_offset = (y_src * width + x_src) as usize;
+            let uv_offset = (uv_start + (y_src / 2) * width + (x_src / 2) * 2) as usize;
+
+            let y = yuv[y_offset] as f32;
+            let u = yuv[uv_offset] as f32;
+            let v = yuv[uv_offset + 1] as f32;
+
+            let r = 1.164 * (y - 16.0) + 1.596 * (v - 128.0);
+            let g = 1.164 * (y - 16.0) - 0.813 * (v - 128.0) - 0.391 * (u - 128.0);
+            let b = 1.164 * (y - 16.0) + 2.018 * (u - 128.0);
+
+            rgba.push(r as u8);
+            rgba.push(g as u8);
+            rgba.push(b as u8);
+            rgba.push(0xFF);
+        }
+    }
+
+    rgba
+}
+
+fn frame() -> Vec<u8> {
+    // a cheap deterministic pattern, so the branch predictor can't learn a constant frame
+    (0..WIDTH * HEIGHT * 3 / 2)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+        .collect()
+}
+
+fn bench(c: &mut Criterion) {
+    let data = frame();
+    let nv12 = yuv::Nv12::new(&data, WIDTH, HEIGHT, None);
+
+    let mut group = c.benchmark_group("nv12_to_rgba");
+    for downscale in [1, 4] {
+        let (width, height) = (WIDTH / downscale, HEIGHT / downscale);
+
+        group.bench_with_input(
+            BenchmarkId::new("legacy", downscale),
+            &downscale,
+            |b, &d| b.iter(|| yuv_to_rgba_legacy(black_box(&data), WIDTH, HEIGHT, d)),
+        );
+        for (name, filter) in [
+            ("nearest", yuv::Filter::Nearest),
+            ("bilinear", yuv::Filter::Bilinear),
+            ("box", yuv::Filter::Box),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, downscale), &downscale, |b, _| {
+                b.iter(|| {
+                    yuv::nv12_to_rgba(black_box(&nv12), width, height, filter, yuv::Matrix::Bt601)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,530 @@
+//! Conversion of ASS/SSA (Advanced SubStation Alpha) events into [`SubtitleCue`]s.
+//!
+//! Styles come from the script header, which Matroska carries in the `codec_data` of the caps, or
+//! from the start of a whole script.
+//! Positioning and the common override tags (`\b`, `\i`, `\u`, `\s`, `\c`, `\an`, `\pos`) are kept;
+//! drawing commands, animation and karaoke are not supported.
+
+use crate::subtitle::{SubtitleCue, escape_markup};
+use iced::Color;
+use iced::alignment::{Horizontal, Vertical};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A style from the `[V4+ Styles]` (or `[V4 Styles]`) section.
+#[derive(Debug, Clone, PartialEq)]
+struct Style {
+    size: f32,
+    color: Color,
+    outline_color: Color,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    /// Numpad alignment, e.g., `2` for bottom center.
+    alignment: u8,
+    margin_l: f32,
+    margin_r: f32,
+    margin_v: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            size: 20.0,
+            color: Color::WHITE,
+            outline_color: Color::BLACK,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            alignment: 2,
+            margin_l: 10.0,
+            margin_r: 10.0,
+            margin_v: 10.0,
+        }
+    }
+}
+
+/// The header of a script, which events are resolved against.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Script {
+    play_res_x: f32,
+    play_res_y: f32,
+    styles: HashMap<String, Style>,
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Script {
+            play_res_x: 384.0,
+            play_res_y: 288.0,
+            styles: HashMap::new(),
+        }
+    }
+}
+
+impl Script {
+    /// Parses the sections of a script before `[Events]`.
+    /// Unknown sections and malformed lines are skipped.
+    pub fn parse(header: &str) -> Self {
+        let mut script = Script::default();
+        let mut section = String::new();
+        let mut style_format: Vec<String> = Vec::new();
+        let mut play_res_x = None;
+        let mut legacy = false;
+
+        for line in header.lines().map(str::trim) {
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line.to_ascii_lowercase();
+                legacy |= section == "[v4 styles]";
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match (section.as_str(), key) {
+                ("[script info]", "PlayResX") => play_res_x = value.parse().ok(),
+                ("[script info]", "PlayResY") => {
+                    if let Ok(y) = value.parse() {
+                        script.play_res_y = y;
+                    }
+                }
+                ("[v4+ styles]" | "[v4 styles]", "Format") => {
+                    style_format = value.split(',').map(|f| f.trim().to_string()).collect();
+                }
+                ("[v4+ styles]" | "[v4 styles]", "Style") => {
+                    let fields: Vec<_> = value.splitn(style_format.len().max(1), ',').collect();
+                    let field = |name: &str| {
+                        style_format
+                            .iter()
+                            .position(|f| f.eq_ignore_ascii_case(name))
+                            .and_then(|i| fields.get(i))
+                            .map(|v| v.trim())
+                    };
+                    let number = |name: &str| field(name).and_then(|v| v.parse::<f32>().ok());
+                    // -1 is true in ASS
+                    let flag = |name: &str| number(name).is_some_and(|v| v != 0.0);
+
+                    let defaults = Style::default();
+                    let alignment = number("Alignment").map_or(defaults.alignment, |a| {
+                        if legacy {
+                            from_legacy_alignment(a as u8)
+                        } else {
+                            a as u8
+                        }
+                    });
+                    let style = Style {
+                        size: number("Fontsize").unwrap_or(defaults.size),
+                        color: field("PrimaryColour")
+                            .and_then(parse_color)
+                            .unwrap_or(defaults.color),
+                        outline_color: field("OutlineColour")
+                            .and_then(parse_color)
+                            .unwrap_or(defaults.outline_color),
+                        bold: flag("Bold"),
+                        italic: flag("Italic"),
+                        underline: flag("Underline"),
+                        strikethrough: flag("StrikeOut"),
+                        alignment,
+                        margin_l: number("MarginL").unwrap_or(defaults.margin_l),
+                        margin_r: number("MarginR").unwrap_or(defaults.margin_r),
+                        margin_v: number("MarginV").unwrap_or(defaults.margin_v),
+                    };
+                    if let Some(name) = field("Name") {
+                        script
+                            .styles
+                            .insert(name.trim_start_matches('*').to_string(), style);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // the default width assumes 4:3 video, so only use it if the height is also defaulted
+        script.play_res_x = play_res_x.unwrap_or(if script.play_res_y == 288.0 {
+            384.0
+        } else {
+            script.play_res_y * 4.0 / 3.0
+        });
+        script
+    }
+
+    /// Converts the fields of an event, in the order given by `format`, into a cue.
+    fn cue(&self, format: &[&str], event: &str) -> SubtitleCue {
+        let fields: Vec<_> = event.splitn(format.len().max(1), ',').collect();
+        let field = |name: &str| {
+            format
+                .iter()
+                .position(|f| f.eq_ignore_ascii_case(name))
+                .and_then(|i| fields.get(i))
+                .map(|v| v.trim())
+        };
+        let style = field("Style")
+            .and_then(|name| self.styles.get(name.trim_start_matches('*')))
+            .cloned()
+            .unwrap_or_default();
+        // margins given by the event override those of the style, unless zero
+        let margin = |name: &str, default: f32| {
+            field(name)
+                .and_then(|v| v.parse::<f32>().ok())
+                .filter(|v| *v != 0.0)
+                .unwrap_or(default)
+        };
+        let margin_l = margin("MarginL", style.margin_l);
+        let margin_r = margin("MarginR", style.margin_r);
+        let margin_v = margin("MarginV", style.margin_v);
+
+        let text = field("Text").unwrap_or_default();
+        let (markup, overrides) = convert_text(text, &style);
+        let alignment = overrides.alignment.unwrap_or(style.alignment);
+
+        let align_x = match alignment % 3 {
+            1 => Horizontal::Left,
+            2 => Horizontal::Center,
+            _ => Horizontal::Right,
+        };
+        let align_y = match alignment {
+            1..=3 => Vertical::Bottom,
+            4..=6 => Vertical::Center,
+            _ => Vertical::Top,
+        };
+        let (x, y) = overrides.position.unwrap_or_else(|| {
+            let x = match align_x {
+                Horizontal::Left => margin_l,
+                Horizontal::Center => self.play_res_x / 2.0,
+                Horizontal::Right => self.play_res_x - margin_r,
+            };
+            let y = match align_y {
+                Vertical::Top => margin_v,
+                Vertical::Center => self.play_res_y / 2.0,
+                Vertical::Bottom => self.play_res_y - margin_v,
+            };
+            (x, y)
+        });
+
+        SubtitleCue {
+            markup,
+            x: Some(x / self.play_res_x),
+            y: Some(y / self.play_res_y),
+            align_x,
+            align_y,
+            color: Some(style.color),
+            outline_color: Some(style.outline_color),
+            size: Some(style.size / self.play_res_y),
+            ..SubtitleCue::default()
+        }
+    }
+
+    /// Converts an event as stored in Matroska (`application/x-ass` or `application/x-ssa` buffers).
+    pub fn matroska_cue(&self, event: &str) -> SubtitleCue {
+        self.cue(
+            &[
+                "ReadOrder",
+                "Layer",
+                "Style",
+                "Name",
+                "MarginL",
+                "MarginR",
+                "MarginV",
+                "Effect",
+                "Text",
+            ],
+            event,
+        )
+    }
+}
+
+/// Override tags which affect the whole event rather than the text following them.
+#[derive(Debug, Default)]
+struct Overrides {
+    alignment: Option<u8>,
+    position: Option<(f32, f32)>,
+}
+
+/// Formatting of a run of text, changed by override tags.
+#[derive(Debug, Clone, PartialEq)]
+struct TextFormat {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    /// Overrides the color of the style.
+    color: Option<Color>,
+}
+
+impl TextFormat {
+    /// Appends `run` to `markup`, wrapped in a span if it's formatted.
+    fn push(&self, run: &mut String, markup: &mut String) {
+        if run.is_empty() {
+            return;
+        }
+        let mut attributes = String::new();
+        if self.bold {
+            attributes.push_str(" weight=\"bold\"");
+        }
+        if self.italic {
+            attributes.push_str(" style=\"italic\"");
+        }
+        if self.underline {
+            attributes.push_str(" underline=\"single\"");
+        }
+        if self.strikethrough {
+            attributes.push_str(" strikethrough=\"true\"");
+        }
+        if let Some(color) = self.color {
+            let [r, g, b, _] = color.into_rgba8();
+            attributes.push_str(&format!(" foreground=\"#{r:02x}{g:02x}{b:02x}\""));
+        }
+
+        if attributes.is_empty() {
+            markup.push_str(run);
+        } else {
+            markup.push_str(&format!("<span{attributes}>{run}</span>"));
+        }
+        run.clear();
+    }
+
+    /// Applies a single override tag, without its leading backslash.
+    fn apply(&mut self, tag: &str, base: &TextFormat, overrides: &mut Overrides) {
+        let toggle = |value: &str| value.parse::<u32>().map(|v| v != 0).ok();
+
+        if let Some(value) = tag.strip_prefix("pos(").and_then(|v| v.strip_suffix(')')) {
+            if let Some((x, y)) = value.split_once(',')
+                && let (Ok(x), Ok(y)) = (x.trim().parse(), y.trim().parse())
+            {
+                overrides.position.get_or_insert((x, y));
+            }
+        } else if let Some(value) = tag.strip_prefix("an") {
+            if let Ok(alignment @ 1..=9) = value.parse() {
+                overrides.alignment.get_or_insert(alignment);
+            }
+        } else if let Some(Ok(alignment @ 1..=11)) = tag.strip_prefix('a').map(str::parse::<u8>) {
+            overrides
+                .alignment
+                .get_or_insert(from_legacy_alignment(alignment));
+        } else if let Some(value) = tag.strip_prefix("1c").or_else(|| tag.strip_prefix('c')) {
+            // an empty value resets the color
+            if value.is_empty() {
+                self.color = base.color;
+            } else if let Some(color) = parse_color(value) {
+                self.color = Some(color);
+            }
+        } else if let Some(value) = tag.strip_prefix('b') {
+            // also accepts weights, e.g., `\b700`
+            self.bold = value
+                .parse::<u32>()
+                .map_or(self.bold, |v| v == 1 || v >= 600);
+        } else if let Some(value) = tag.strip_prefix('i') {
+            self.italic = toggle(value).unwrap_or(self.italic);
+        } else if let Some(value) = tag.strip_prefix('u') {
+            self.underline = toggle(value).unwrap_or(self.underline);
+        } else if let Some(value) = tag.strip_prefix('s') {
+            self.strikethrough = toggle(value).unwrap_or(self.strikethrough);
+        } else if tag.starts_with('r') {
+            *self = base.clone();
+        }
+    }
+}
+
+/// Converts the text of an event, with its override blocks, into Pango markup.
+fn convert_text(text: &str, style: &Style) -> (String, Overrides) {
+    let base = TextFormat {
+        bold: style.bold,
+        italic: style.italic,
+        underline: style.underline,
+        strikethrough: style.strikethrough,
+        color: None,
+    };
+    let mut format = base.clone();
+    let mut overrides = Overrides::default();
+    let mut markup = String::new();
+    let mut run = String::new();
+
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(block) = rest.strip_prefix('{')
+            && let Some(end) = block.find('}')
+        {
+            format.push(&mut run, &mut markup);
+            for tag in block[..end]
+                .split('\\')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+            {
+                format.apply(tag, &base, &mut overrides);
+            }
+            rest = &block[end + 1..];
+            continue;
+        }
+
+        let escape = rest.strip_prefix('\\').and_then(|escaped| {
+            let c = match escaped.chars().next()? {
+                'N' => '\n',
+                // soft line breaks only apply with a wrapping style we don't implement
+                'n' => ' ',
+                'h' => '\u{a0}',
+                _ => return None,
+            };
+            Some((c, 2))
+        });
+        let (c, length) = escape.unwrap_or((c, c.len_utf8()));
+        run.push_str(&escape_markup(c.encode_utf8(&mut [0; 4])));
+        rest = &rest[length..];
+    }
+    format.push(&mut run, &mut markup);
+
+    (markup, overrides)
+}
+
+/// Converts SSA alignment (1-3 bottom, +4 top, +8 middle) to numpad alignment.
+fn from_legacy_alignment(alignment: u8) -> u8 {
+    match alignment {
+        5..=7 => alignment + 2,
+        9..=11 => alignment - 5,
+        _ => alignment,
+    }
+}
+
+/// Parses `&HAABBGGRR&` (or `&HBBGGRR&`), where an alpha of `00` is opaque.
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value
+        .trim()
+        .trim_start_matches('&')
+        .trim_start_matches(['H', 'h'])
+        .trim_end_matches('&');
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [a, b, g, r] = value.to_be_bytes();
+    let a = if hex.len() > 6 { a } else { 0 };
+    Some(Color::from_rgba8(r, g, b, 1.0 - a as f32 / 255.0))
+}
+
+/// Parses the events of a whole script into cues ordered by start time.
+/// Comments and malformed lines are skipped.
+pub(crate) fn parse_file(text: &str) -> Vec<SubtitleCue> {
+    let script = Script::parse(text);
+    let mut events = false;
+    let mut format: Vec<&str> = Vec::new();
+    let mut cues = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            events = line.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':').filter(|_| events) else {
+            continue;
+        };
+
+        match key {
+            "Format" => format = value.split(',').map(str::trim).collect(),
+            "Dialogue" if !format.is_empty() => {
+                let fields: Vec<_> = value.splitn(format.len(), ',').collect();
+                let time = |name: &str| {
+                    format
+                        .iter()
+                        .position(|f| f.eq_ignore_ascii_case(name))
+                        .and_then(|i| fields.get(i))
+                        .and_then(|v| parse_time(v.trim()))
+                };
+                let (Some(start), Some(end)) = (time("Start"), time("End")) else {
+                    continue;
+                };
+                cues.push(SubtitleCue {
+                    start,
+                    end,
+                    ..script.cue(&format, value)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    cues.sort_by_key(|cue| cue.start);
+    cues
+}
+
+/// Parses `H:MM:SS.cc`.
+fn parse_time(value: &str) -> Option<Duration> {
+    let mut parts = value.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let whole = hours
+        .checked_mul(3600)?
+        .checked_add(minutes.checked_mul(60)?)?;
+    // rejects negative, infinite and NaN seconds
+    Duration::from_secs(whole).checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(
+            parse_time("1:02:03.25"),
+            Some(Duration::from_millis(3_723_250))
+        );
+        assert_eq!(parse_time("0:00:01.5"), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for value in [
+            "",
+            "0:00",
+            "-1:00:00.00",
+            "0:00:-1.00",
+            "0:00:inf",
+            "0:00:NaN",
+            "0:00:1e400",
+            "18446744073709551615:00:00.00",
+            "0:18446744073709551615:00.00",
+        ] {
+            assert_eq!(parse_time(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn parses_file() {
+        let cues = parse_file(
+            "[Script Info]\n\
+             PlayResX: 640\n\
+             PlayResY: 480\n\
+             \n\
+             [V4+ Styles]\n\
+             Format: Name, Fontsize, PrimaryColour, Bold, Alignment, MarginL, MarginR, MarginV\n\
+             Style: Default,48,&H0000FFFF,-1,2,10,10,20\n\
+             \n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+             Dialogue: 0,0:00:05.00,0:00:06.00,Default,,0,0,0,,{\\an8}Top\n\
+             Comment: 0,0:00:00.00,0:00:09.00,Default,,0,0,0,,Skipped\n\
+             Dialogue: 0,0:00:-1.00,0:00:02.00,Default,,0,0,0,,Skipped\n\
+             Dialogue: 0,0:00:01.00,0:00:02.50,Default,,0,0,0,,{\\i1}First{\\i0}\\NLine, with comma\n",
+        );
+        assert_eq!(cues.len(), 2);
+
+        assert_eq!(cues[0].start, Duration::from_secs(1));
+        assert_eq!(cues[0].end, Duration::from_millis(2500));
+        assert_eq!(
+            cues[0].markup,
+            "<span weight=\"bold\" style=\"italic\">First</span>\
+             <span weight=\"bold\">\nLine, with comma</span>"
+        );
+        assert_eq!(cues[0].color, Some(Color::from_rgb8(255, 255, 0)));
+        assert_eq!(cues[0].size, Some(48.0 / 480.0));
+        assert_eq!(cues[0].x, Some(0.5));
+        assert_eq!(cues[0].y, Some(460.0 / 480.0));
+        assert_eq!(cues[0].align_y, Vertical::Bottom);
+
+        assert_eq!(cues[1].start, Duration::from_secs(5));
+        assert_eq!(cues[1].markup, "<span weight=\"bold\">Top</span>");
+        assert_eq!(cues[1].y, Some(20.0 / 480.0));
+        assert_eq!(cues[1].align_y, Vertical::Top);
+    }
+}
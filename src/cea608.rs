@@ -0,0 +1,515 @@
+//! Decoding of CEA-608 closed captions, on their own or carried by CEA-708, into [`SubtitleCue`]s.
+//!
+//! Only the first caption channel (CC1) is decoded, in pop-on, roll-up and paint-on modes, keeping
+//! the row position along with italics, underline and colors. Text mode and CEA-708 services are not
+//! supported, but most CEA-708 streams also carry their captions as CEA-608.
+
+use crate::subtitle::{SubtitleCue, escape_markup};
+use gstreamer_video::VideoCaptionType;
+use iced::alignment::{Horizontal, Vertical};
+use std::time::Duration;
+
+const ROWS: usize = 15;
+const COLUMNS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Attributes {
+    color: Option<&'static str>,
+    italic: bool,
+    underline: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    character: char,
+    attributes: Attributes,
+}
+
+type Memory = [[Option<Cell>; COLUMNS]; ROWS];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    PopOn,
+    PaintOn,
+    /// Roll-up with the given number of rows.
+    RollUp(usize),
+    /// Text mode, which is ignored.
+    Text,
+}
+
+/// Decodes the caption data attached to successive frames.
+#[derive(Debug)]
+pub(crate) struct Decoder {
+    mode: Mode,
+    displayed: Memory,
+    non_displayed: Memory,
+    row: usize,
+    column: usize,
+    attributes: Attributes,
+    /// Whether the last control code was for the first channel, which the following text belongs to.
+    channel_1: bool,
+    /// Control codes are sent twice, so the repeat of the last one is skipped.
+    last_control: Option<[u8; 2]>,
+    /// The displayed memory changed since the last cue.
+    changed: bool,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Decoder {
+            mode: Mode::PopOn,
+            displayed: [[None; COLUMNS]; ROWS],
+            non_displayed: [[None; COLUMNS]; ROWS],
+            row: ROWS - 1,
+            column: 0,
+            attributes: Attributes::default(),
+            channel_1: true,
+            last_control: None,
+            changed: false,
+        }
+    }
+}
+
+impl Decoder {
+    /// Decodes the caption data of one frame. Returns the new captions if the displayed ones
+    /// changed, with `None` once they're cleared; `start` becomes the start of the cue.
+    pub fn decode(
+        &mut self,
+        caption_type: VideoCaptionType,
+        data: &[u8],
+        start: Duration,
+    ) -> Option<Option<SubtitleCue>> {
+        for pair in field_1_pairs(caption_type, data) {
+            self.decode_pair(pair);
+        }
+
+        if !std::mem::take(&mut self.changed) {
+            return None;
+        }
+        Some(self.cue(start))
+    }
+
+    fn decode_pair(&mut self, pair: [u8; 2]) {
+        // strip the odd parity bit
+        let [b1, b2] = pair.map(|b| b & 0x7F);
+
+        if (0x10..=0x1F).contains(&b1) {
+            if self.last_control == Some([b1, b2]) {
+                self.last_control = None;
+                return;
+            }
+            self.last_control = Some([b1, b2]);
+            self.channel_1 = b1 & 0x08 == 0;
+            if self.channel_1 {
+                self.control(b1, b2);
+            }
+            return;
+        }
+
+        self.last_control = None;
+        if !self.channel_1 || b1 < 0x20 {
+            return;
+        }
+        self.write(basic_character(b1));
+        if b2 >= 0x20 {
+            self.write(basic_character(b2));
+        }
+    }
+
+    fn control(&mut self, b1: u8, b2: u8) {
+        match (b1, b2) {
+            // special characters, which use the current attributes
+            (0x11, 0x30..=0x3F) => self.write(SPECIAL[(b2 - 0x30) as usize]),
+            // mid-row codes, which change the attributes and take a space
+            (0x11, 0x20..=0x2F) => {
+                self.attributes = style_attributes(b2 & 0x0F);
+                self.write(' ');
+            }
+            // extended characters, which replace the standard character sent before them
+            (0x12 | 0x13, 0x20..=0x3F) => {
+                self.column = self.column.saturating_sub(1);
+                let table = if b1 == 0x12 { &EXTENDED_1 } else { &EXTENDED_2 };
+                self.write(table[(b2 - 0x20) as usize]);
+            }
+            (0x14 | 0x15, 0x20..=0x2F) => self.command(b2),
+            // tab offsets
+            (0x17, 0x21..=0x23) => {
+                self.column = (self.column + (b2 - 0x20) as usize).min(COLUMNS - 1)
+            }
+            (0x10..=0x17, 0x40..=0x7F) => self.preamble(b1, b2),
+            _ => {}
+        }
+    }
+
+    fn command(&mut self, command: u8) {
+        match command {
+            // resume caption loading
+            0x20 => self.mode = Mode::PopOn,
+            // backspace
+            0x21 => {
+                self.column = self.column.saturating_sub(1);
+                let (row, column) = (self.row, self.column);
+                if let Some(memory) = self.target() {
+                    memory[row][column] = None;
+                }
+            }
+            // delete to end of row
+            0x24 => {
+                let (row, column) = (self.row, self.column);
+                if let Some(memory) = self.target() {
+                    memory[row][column..].fill(None);
+                }
+            }
+            // roll-up with 2, 3 or 4 rows
+            0x25..=0x27 => {
+                let rows = (command - 0x23) as usize;
+                if !matches!(self.mode, Mode::RollUp(_)) {
+                    self.displayed = [[None; COLUMNS]; ROWS];
+                    self.changed = true;
+                    self.row = ROWS - 1;
+                }
+                self.mode = Mode::RollUp(rows);
+                self.column = 0;
+            }
+            // resume direct captioning
+            0x29 => self.mode = Mode::PaintOn,
+            // text restart and resume text display
+            0x2A | 0x2B => self.mode = Mode::Text,
+            // erase displayed memory
+            0x2C => {
+                self.displayed = [[None; COLUMNS]; ROWS];
+                self.changed = true;
+            }
+            // carriage return
+            0x2D => {
+                if let Mode::RollUp(rows) = self.mode {
+                    let top = (self.row + 1).saturating_sub(rows);
+                    self.displayed.copy_within(top + 1..=self.row, top);
+                    self.displayed[self.row] = [None; COLUMNS];
+                    self.displayed[..top].fill([None; COLUMNS]);
+                    self.changed = true;
+                }
+                self.column = 0;
+            }
+            // erase non-displayed memory
+            0x2E => self.non_displayed = [[None; COLUMNS]; ROWS],
+            // end of caption
+            0x2F => {
+                std::mem::swap(&mut self.displayed, &mut self.non_displayed);
+                self.mode = Mode::PopOn;
+                self.changed = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies a preamble address code, which moves the cursor and sets the attributes.
+    fn preamble(&mut self, b1: u8, b2: u8) {
+        let upper = b2 & 0x20 == 0;
+        let row = match b1 {
+            0x11 => 1,
+            0x12 => 3,
+            0x15 => 5,
+            0x16 => 7,
+            0x17 => 9,
+            0x10 => 11,
+            0x13 => 12,
+            _ => 14,
+        } + usize::from(!upper && b1 != 0x10);
+
+        let attributes = b2 & 0x1F;
+        if attributes >= 0x10 {
+            // indents are in steps of 4 columns and reset the style
+            self.column = ((attributes & 0x0E) >> 1) as usize * 4;
+            self.attributes = Attributes {
+                underline: attributes & 0x01 != 0,
+                ..Attributes::default()
+            };
+        } else {
+            self.column = 0;
+            self.attributes = style_attributes(attributes);
+        }
+
+        let row = row - 1;
+        if let Mode::RollUp(rows) = self.mode {
+            // the roll-up window moves along with its base row
+            if row != self.row {
+                let rows = rows.min(row + 1).min(self.row + 1);
+                let window: Vec<_> = self.displayed[self.row + 1 - rows..=self.row].to_vec();
+                self.displayed = [[None; COLUMNS]; ROWS];
+                self.displayed[row + 1 - rows..=row].copy_from_slice(&window);
+                self.changed = true;
+            }
+        }
+        self.row = row;
+    }
+
+    /// The memory written by characters in the current mode.
+    fn target(&mut self) -> Option<&mut Memory> {
+        match self.mode {
+            Mode::PopOn => Some(&mut self.non_displayed),
+            Mode::PaintOn | Mode::RollUp(_) => {
+                self.changed = true;
+                Some(&mut self.displayed)
+            }
+            Mode::Text => None,
+        }
+    }
+
+    fn write(&mut self, character: char) {
+        let (row, column, attributes) = (self.row, self.column, self.attributes);
+        if let Some(memory) = self.target() {
+            memory[row][column] = Some(Cell {
+                character,
+                attributes,
+            });
+            self.column = (column + 1).min(COLUMNS - 1);
+        }
+    }
+
+    /// Converts the displayed memory into a cue placed at its first row.
+    fn cue(&self, start: Duration) -> Option<SubtitleCue> {
+        let rows: Vec<_> = self
+            .displayed
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(Option::is_some))
+            .collect();
+        let first = rows.first()?.0;
+
+        let markup = rows
+            .iter()
+            .map(|(_, row)| row_markup(row))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(SubtitleCue {
+            start,
+            // captions stay until they're replaced
+            end: Duration::MAX,
+            markup,
+            // rows span the middle 80% of the height
+            y: Some(0.1 + first as f32 / ROWS as f32 * 0.8),
+            align_x: Horizontal::Center,
+            align_y: Vertical::Top,
+            ..SubtitleCue::default()
+        })
+    }
+
+    /// Forgets all captions, e.g., after a seek.
+    pub fn reset(&mut self) {
+        *self = Decoder::default();
+    }
+}
+
+/// Converts a row into markup, with a span per run of attributes. Empty cells become spaces,
+/// except at the ends of the row.
+fn row_markup(row: &[Option<Cell>; COLUMNS]) -> String {
+    let first = row.iter().position(Option::is_some).unwrap_or(0);
+    let last = row.iter().rposition(Option::is_some).unwrap_or(0);
+
+    let mut markup = String::new();
+    let mut current = Attributes::default();
+    let mut open = false;
+    for cell in &row[first..=last] {
+        let (character, attributes) = match cell {
+            Some(cell) => (cell.character, cell.attributes),
+            None => (' ', current),
+        };
+        if attributes != current || !open {
+            if open {
+                markup.push_str("</span>");
+            }
+            markup.push_str("<span");
+            if let Some(color) = attributes.color {
+                markup.push_str(&format!(" foreground=\"{color}\""));
+            }
+            if attributes.italic {
+                markup.push_str(" style=\"italic\"");
+            }
+            if attributes.underline {
+                markup.push_str(" underline=\"single\"");
+            }
+            markup.push('>');
+            current = attributes;
+            open = true;
+        }
+        markup.push_str(&escape_markup(character.encode_utf8(&mut [0; 4])));
+    }
+    if open {
+        markup.push_str("</span>");
+    }
+    markup
+}
+
+/// Attributes of the style bits of preamble address and mid-row codes.
+fn style_attributes(bits: u8) -> Attributes {
+    let color = match (bits & 0x0E) >> 1 {
+        1 => Some("#00ff00"),
+        2 => Some("#0000ff"),
+        3 => Some("#00ffff"),
+        4 => Some("#ff0000"),
+        5 => Some("#ffff00"),
+        6 => Some("#ff00ff"),
+        _ => None,
+    };
+    Attributes {
+        color,
+        italic: (bits & 0x0E) >> 1 == 7,
+        underline: bits & 0x01 != 0,
+    }
+}
+
+/// Collects the byte pairs of the first field from caption data of any type.
+fn field_1_pairs(caption_type: VideoCaptionType, data: &[u8]) -> Vec<[u8; 2]> {
+    let triplets = |data: &[u8], field_1: fn(u8) -> bool| -> Vec<[u8; 2]> {
+        data.chunks_exact(3)
+            .filter(|triplet| field_1(triplet[0]))
+            .map(|triplet| [triplet[1], triplet[2]])
+            .collect()
+    };
+    // a valid CEA-608 pair of the first field in CEA-708 `cc_data`
+    let cc_data_field_1 = |header: u8| header & 0x04 != 0 && header & 0x03 == 0;
+
+    match caption_type {
+        VideoCaptionType::Cea608Raw => data
+            .chunks_exact(2)
+            .map(|pair| [pair[0], pair[1]])
+            .collect(),
+        VideoCaptionType::Cea608S3341a => triplets(data, |field| field & 0x80 != 0),
+        VideoCaptionType::Cea708Raw => triplets(data, cc_data_field_1),
+        VideoCaptionType::Cea708Cdp => {
+            // skip the header, and the time code section if there is one
+            let Some(&flags) = data.get(4) else {
+                return Vec::new();
+            };
+            let offset = if flags & 0x80 != 0 { 12 } else { 7 };
+            match data.get(offset..offset + 2) {
+                Some([0x72, count]) => {
+                    let count = (count & 0x1F) as usize;
+                    let start = offset + 2;
+                    let end = (start + count * 3).min(data.len());
+                    triplets(&data[start..end], cc_data_field_1)
+                }
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Maps a standard character code, which is mostly ASCII.
+fn basic_character(code: u8) -> char {
+    match code {
+        0x2A => 'á',
+        0x5C => 'é',
+        0x5E => 'í',
+        0x5F => 'ó',
+        0x60 => 'ú',
+        0x7B => 'ç',
+        0x7C => '÷',
+        0x7D => 'Ñ',
+        0x7E => 'ñ',
+        0x7F => '█',
+        code => code as char,
+    }
+}
+
+const SPECIAL: [char; 16] = [
+    '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', '\u{a0}', 'è', 'â', 'ê', 'î', 'ô', 'û',
+];
+
+/// Spanish, miscellaneous and French extended characters.
+const EXTENDED_1: [char; 32] = [
+    'Á', 'É', 'Ó', 'Ú', 'Ü', 'ü', '‘', '¡', '*', '\'', '—', '©', '℠', '•', '“', '”', 'À', 'Â', 'Ç',
+    'È', 'Ê', 'Ë', 'ë', 'Î', 'Ï', 'ï', 'Ô', 'Ù', 'ù', 'Û', '«', '»',
+];
+
+/// Portuguese, German and Danish extended characters.
+const EXTENDED_2: [char; 32] = [
+    'Ã', 'ã', 'Í', 'Ì', 'ì', 'Ò', 'ò', 'Õ', 'õ', '{', '}', '\\', '^', '_', '|', '~', 'Ä', 'ä', 'Ö',
+    'ö', 'ß', '¥', '¤', '│', 'Å', 'å', 'Ø', 'ø', '┌', '┐', '└', '┘',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RCL: [u8; 2] = [0x14, 0x20];
+    const RU2: [u8; 2] = [0x14, 0x25];
+    const EDM: [u8; 2] = [0x14, 0x2C];
+    const CR: [u8; 2] = [0x14, 0x2D];
+    const ENM: [u8; 2] = [0x14, 0x2E];
+    const EOC: [u8; 2] = [0x14, 0x2F];
+    /// Preamble address code of row 15, column 0.
+    const PAC_15: [u8; 2] = [0x14, 0x70];
+    const ITALICS: [u8; 2] = [0x11, 0x2E];
+
+    /// Decodes one frame of `Cea608Raw` pairs, with their odd parity bits set as in a stream.
+    fn decode(decoder: &mut Decoder, pairs: &[[u8; 2]]) -> Option<Option<SubtitleCue>> {
+        let parity = |b: u8| if b.count_ones() % 2 == 0 { b | 0x80 } else { b };
+        let data: Vec<_> = pairs.iter().flatten().copied().map(parity).collect();
+        decoder.decode(VideoCaptionType::Cea608Raw, &data, Duration::from_secs(1))
+    }
+
+    fn markup(cue: Option<Option<SubtitleCue>>) -> Option<Option<String>> {
+        cue.map(|cue| cue.map(|cue| cue.markup))
+    }
+
+    #[test]
+    fn pop_on() {
+        let mut decoder = Decoder::default();
+
+        // control codes are sent twice, and loading into non-displayed memory shows nothing yet
+        let loaded = decode(
+            &mut decoder,
+            &[
+                RCL,
+                RCL,
+                ENM,
+                ENM,
+                PAC_15,
+                PAC_15,
+                [b'H', b'I'],
+                ITALICS,
+                ITALICS,
+                [b'A', 0x80],
+            ],
+        );
+        assert_eq!(loaded, None);
+
+        let cue = decode(&mut decoder, &[EOC, EOC]).unwrap().unwrap();
+        assert_eq!(
+            cue.markup,
+            "<span>HI</span><span style=\"italic\"> A</span>"
+        );
+        assert_eq!(cue.start, Duration::from_secs(1));
+        assert_eq!(cue.end, Duration::MAX);
+        assert_eq!(cue.y, Some(0.1 + 14.0 / 15.0 * 0.8));
+
+        assert_eq!(markup(decode(&mut decoder, &[EDM, EDM])), Some(None));
+        assert_eq!(decode(&mut decoder, &[]), None);
+    }
+
+    #[test]
+    fn roll_up() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(markup(decode(&mut decoder, &[RU2, RU2])), Some(None));
+        assert_eq!(
+            markup(decode(&mut decoder, &[[b'A', b'B']])),
+            Some(Some("<span>AB</span>".to_string()))
+        );
+        assert_eq!(
+            markup(decode(&mut decoder, &[CR, CR, [b'C', b'D']])),
+            Some(Some("<span>AB</span>\n<span>CD</span>".to_string()))
+        );
+
+        // the top row scrolls out of the two row window
+        let cue = decode(&mut decoder, &[CR, CR, [b'E', b'F']])
+            .unwrap()
+            .unwrap();
+        assert_eq!(cue.markup, "<span>CD</span>\n<span>EF</span>");
+        assert_eq!(cue.y, Some(0.1 + 13.0 / 15.0 * 0.8));
+    }
+}
@@ -0,0 +1,67 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// Maximum number of parameters passed to an [`Effect`] by each [`VideoPlayer`](crate::VideoPlayer).
+pub const MAX_EFFECT_PARAMS: usize = 16;
+
+/// A custom post-processing pass written in WGSL, applied after YUV conversion.
+///
+/// The source must define a function which maps the color of a pixel to its new color:
+///
+/// ```wgsl
+/// fn effect(color: vec4<f32>, uv: vec2<f32>) -> vec4<f32> {
+///     let gray = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+///     return vec4<f32>(mix(color.rgb, vec3<f32>(gray), param(0u)), color.a);
+/// }
+/// ```
+///
+/// The following are available to the source:
+/// - `input`, a `texture_2d<f32>` holding the output of the previous pass, and `input_sampler`, for sampling neighbouring pixels.
+/// - `texel_size`, a `vec2<f32>` holding the size of one pixel of `input` in texture coordinates.
+/// - `param(i: u32) -> f32`, returning the `i`th parameter given to [`VideoPlayer::effect`](crate::VideoPlayer::effect).
+///
+/// Colors are premultiplied by their alpha, which is below `1.0` at rounded corners and with reduced opacity.
+///
+/// Effects are compiled the first time they are drawn, and shared by every effect with the same source,
+/// so they can be created in `view`. Effects with invalid WGSL are logged and skipped. Backends
+/// validating asynchronously (e.g., WebGPU) only draw an effect once it's validated, a few frames later.
+/// They are not applied when a renderer without primitive support falls back to CPU rendering.
+#[derive(Debug, Clone)]
+pub struct Effect {
+    /// Hash of the source, identifying the compiled pipeline.
+    pub(crate) id: u64,
+    pub(crate) label: Arc<str>,
+    pub(crate) source: Arc<str>,
+}
+
+impl Effect {
+    /// Creates an effect from WGSL `source` defining an `effect` function.
+    pub fn new(label: impl Into<String>, source: impl Into<String>) -> Self {
+        let source: Arc<str> = source.into().into();
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+
+        Effect {
+            id: hasher.finish(),
+            label: label.into().into(),
+            source,
+        }
+    }
+
+    /// Get the label of the effect.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Builds the full shader module, wrapping the user source with the bindings and entry points.
+    pub(crate) fn module_source(&self) -> String {
+        format!("{}\n{}", include_str!("effect.wgsl"), self.source)
+    }
+}
+
+/// An [`Effect`] along with the parameters given to it by a [`VideoPlayer`](crate::VideoPlayer).
+#[derive(Debug, Clone)]
+pub(crate) struct EffectInstance {
+    pub effect: Effect,
+    pub params: [f32; MAX_EFFECT_PARAMS],
+}
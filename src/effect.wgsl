@@ -0,0 +1,51 @@
+// prepended to the source of every `Effect`, which provides `fn effect(color: vec4<f32>, uv: vec2<f32>) -> vec4<f32>`
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct EffectUniforms {
+    rect: vec4<f32>,
+    params: array<vec4<f32>, 4>,
+    texel_size: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var input: texture_2d<f32>;
+
+@group(0) @binding(1)
+var input_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> effect_uniforms: EffectUniforms;
+
+var<private> texel_size: vec2<f32>;
+
+fn param(i: u32) -> f32 {
+    return effect_uniforms.params[i / 4u][i % 4u];
+}
+
+@vertex
+fn effect_vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let rect = effect_uniforms.rect;
+    var quad = array<vec4<f32>, 6>(
+        vec4<f32>(rect.xy, 0.0, 0.0),
+        vec4<f32>(rect.zy, 1.0, 0.0),
+        vec4<f32>(rect.xw, 0.0, 1.0),
+        vec4<f32>(rect.zy, 1.0, 0.0),
+        vec4<f32>(rect.zw, 1.0, 1.0),
+        vec4<f32>(rect.xw, 0.0, 1.0),
+    );
+
+    var out: VertexOutput;
+    out.uv = quad[in_vertex_index].zw;
+    out.position = vec4<f32>(quad[in_vertex_index].xy, 1.0, 1.0);
+    return out;
+}
+
+@fragment
+fn effect_fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    texel_size = effect_uniforms.texel_size;
+    return effect(textureSample(input, input_sampler, in.uv), in.uv);
+}
@@ -34,16 +34,34 @@
 //!
 //! You can programmatically control the video (e.g., seek, pause, loop, grab thumbnails) by accessing various methods on [`Video`].
 
+mod ass;
+mod cea608;
+mod effect;
+mod lut;
 mod pipeline;
+mod renderer;
+mod srt;
+mod style;
+mod subtitle;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod video;
 mod video_player;
+mod waveform;
+mod webvtt;
+mod yuv;
 
 use gstreamer as gst;
 use thiserror::Error;
 
-pub use video::Position;
+pub use effect::{Effect, MAX_EFFECT_PARAMS};
+pub use lut::LutHandle;
+pub use renderer::Renderer;
+pub use style::{Catalog, Style, StyleFn};
+pub use subtitle::{SubtitleCue, SubtitleFormat, SubtitleStyle};
 pub use video::Video;
-pub use video_player::VideoPlayer;
+pub use video::{AudioLevels, AudioOutput, DeinterlaceMode, Delay, Position};
+pub use video_player::{FilterMode, VideoPlayer};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -73,4 +91,10 @@ pub enum Error {
     Lock,
     #[error("invalid framerate: {0}")]
     Framerate(f64),
+    #[error("invalid LUT: {0}")]
+    Lut(String),
+    #[error("no audio output device with the id '{0}'")]
+    AudioDevice(String),
+    #[error("failed to decode the audio waveform: {0}")]
+    Waveform(String),
 }
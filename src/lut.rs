@@ -0,0 +1,220 @@
+use crate::Error;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A 3D color lookup table, used for color grading with [`VideoPlayer::lut`](crate::VideoPlayer::lut).
+///
+/// Cheap to clone; the table is uploaded to the GPU once and shared by every player using it.
+#[derive(Debug, Clone)]
+pub struct LutHandle {
+    pub(crate) id: u64,
+    pub(crate) size: u32,
+    /// Texels packed as `Rgb10a2Unorm`, with red varying fastest, then green, then blue.
+    pub(crate) texels: Arc<[u32]>,
+    pub(crate) domain_min: [f32; 3],
+    pub(crate) domain_max: [f32; 3],
+    title: Option<String>,
+}
+
+impl LutHandle {
+    /// Loads a LUT from an Adobe/Resolve `.cube` file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_cube(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a LUT from the contents of an Adobe/Resolve `.cube` file.
+    ///
+    /// Only 3D LUTs are supported. Output values are clamped to `0.0..=1.0`.
+    pub fn from_cube(cube: &str) -> Result<Self, Error> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let invalid =
+            |line: usize, reason: &str| Error::Lut(format!("line {}: {reason}", line + 1));
+
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut texels = Vec::new();
+
+        for (i, line) in cube.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            let floats = |count: usize| -> Result<Vec<f32>, Error> {
+                let values = rest
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| invalid(i, "expected numbers"))?;
+                if values.len() == count {
+                    Ok(values)
+                } else {
+                    Err(invalid(i, &format!("expected {count} numbers")))
+                }
+            };
+
+            match keyword {
+                "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+                "LUT_3D_SIZE" => {
+                    let n = rest
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|n| (2..=256).contains(n))
+                        .ok_or_else(|| invalid(i, "LUT_3D_SIZE must be between 2 and 256"))?;
+                    texels.reserve((n * n * n) as usize);
+                    size = Some(n);
+                }
+                "LUT_1D_SIZE" => return Err(invalid(i, "1D LUTs are not supported")),
+                "DOMAIN_MIN" => domain_min.copy_from_slice(&floats(3)?),
+                "DOMAIN_MAX" => domain_max.copy_from_slice(&floats(3)?),
+                // Resolve's shorthand for a uniform domain
+                "LUT_3D_INPUT_RANGE" => {
+                    let range = floats(2)?;
+                    domain_min = [range[0]; 3];
+                    domain_max = [range[1]; 3];
+                }
+                _ if keyword.parse::<f32>().is_ok() => {
+                    let rgb = line
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<Vec<f32>, _>>()
+                        .ok()
+                        .filter(|rgb| rgb.len() == 3)
+                        .ok_or_else(|| invalid(i, "expected an RGB triplet"))?;
+                    texels.push(pack_rgb10a2(rgb[0], rgb[1], rgb[2]));
+                }
+                // unknown keywords are allowed by the specification
+                _ => {}
+            }
+        }
+
+        let size = size.ok_or_else(|| Error::Lut("missing LUT_3D_SIZE".to_string()))?;
+        if texels.len() != (size * size * size) as usize {
+            return Err(Error::Lut(format!(
+                "expected {} entries, found {}",
+                size * size * size,
+                texels.len()
+            )));
+        }
+        // NaN would pass the comparison below
+        if domain_min.iter().chain(&domain_max).any(|v| !v.is_finite()) {
+            return Err(Error::Lut(
+                "DOMAIN_MIN and DOMAIN_MAX must be finite".to_string(),
+            ));
+        }
+        if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+            return Err(Error::Lut("DOMAIN_MAX must exceed DOMAIN_MIN".to_string()));
+        }
+
+        Ok(LutHandle {
+            id: NEXT_ID.fetch_add(1, Ordering::SeqCst),
+            size,
+            texels: texels.into(),
+            domain_min,
+            domain_max,
+            title,
+        })
+    }
+
+    /// Get the number of entries along each axis of the LUT.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Get the title of the LUT, if the file specifies one.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+}
+
+pub(crate) fn pack_rgb10a2(r: f32, g: f32, b: f32) -> u32 {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 1023.0).round() as u32;
+    channel(r) | channel(g) << 10 | channel(b) << 20 | 0b11 << 30
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An identity LUT of `size` entries per axis, following `header`.
+    fn cube(header: &str, size: u32) -> String {
+        let mut cube = format!("{header}\nLUT_3D_SIZE {size}\n");
+        let max = (size - 1) as f32;
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let line =
+                        format!("{} {} {}\n", r as f32 / max, g as f32 / max, b as f32 / max);
+                    cube.push_str(&line);
+                }
+            }
+        }
+        cube
+    }
+
+    fn error(cube: &str) -> String {
+        match LutHandle::from_cube(cube) {
+            Err(Error::Lut(reason)) => reason,
+            result => panic!("expected a LUT error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_identity() {
+        let lut = LutHandle::from_cube(&cube("# comment\nTITLE \"Identity\"", 2)).unwrap();
+        assert_eq!(lut.size(), 2);
+        assert_eq!(lut.title(), Some("Identity"));
+        assert_eq!(lut.domain_min, [0.0; 3]);
+        assert_eq!(lut.domain_max, [1.0; 3]);
+        // red varies fastest
+        assert_eq!(lut.texels[1], pack_rgb10a2(1.0, 0.0, 0.0));
+        assert_eq!(lut.texels[7], pack_rgb10a2(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn limits_size() {
+        assert!(LutHandle::from_cube(&cube("", 256)).is_ok());
+        assert!(error("LUT_3D_SIZE 1\n0 0 0").contains("between 2 and 256"));
+        assert!(error("LUT_3D_SIZE 257").contains("between 2 and 256"));
+        assert!(error("LUT_3D_SIZE -2").contains("between 2 and 256"));
+        assert_eq!(error("0 0 0"), "missing LUT_3D_SIZE");
+    }
+
+    #[test]
+    fn reads_domains() {
+        let lut = LutHandle::from_cube(&cube("DOMAIN_MIN 0 0.1 0.2\nDOMAIN_MAX 1 2 3", 2)).unwrap();
+        assert_eq!(lut.domain_min, [0.0, 0.1, 0.2]);
+        assert_eq!(lut.domain_max, [1.0, 2.0, 3.0]);
+
+        let lut = LutHandle::from_cube(&cube("LUT_3D_INPUT_RANGE -0.5 1.5", 2)).unwrap();
+        assert_eq!(lut.domain_min, [-0.5; 3]);
+        assert_eq!(lut.domain_max, [1.5; 3]);
+
+        assert!(error(&cube("DOMAIN_MIN 0 0", 2)).contains("expected 3 numbers"));
+        assert!(error(&cube("LUT_3D_INPUT_RANGE 0 a", 2)).contains("expected numbers"));
+        assert!(error(&cube("DOMAIN_MIN 1 0 0", 2)).contains("must exceed"));
+        assert!(error(&cube("DOMAIN_MAX NaN 1 1", 2)).contains("must be finite"));
+        assert!(error(&cube("DOMAIN_MIN -inf 0 0", 2)).contains("must be finite"));
+    }
+
+    #[test]
+    fn rejects_1d_luts() {
+        assert!(error("LUT_1D_SIZE 2\n0 0 0\n1 1 1").contains("1D LUTs are not supported"));
+    }
+
+    #[test]
+    fn rejects_wrong_data() {
+        let short = cube("", 2).replace("1 1 1\n", "");
+        assert_eq!(error(&short), "expected 8 entries, found 7");
+        let extra = cube("", 2) + "0 0 0\n";
+        assert_eq!(error(&extra), "expected 8 entries, found 9");
+        let pair = cube("", 2).replace("1 1 1", "1 1");
+        assert!(error(&pair).contains("expected an RGB triplet"));
+    }
+}
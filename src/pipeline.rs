@@ -1,42 +1,195 @@
+use crate::effect::{EffectInstance, MAX_EFFECT_PARAMS};
+use crate::lut::{self, LutHandle};
+use crate::subtitle::Bitmap;
 use crate::video::Frame;
+use crate::video_player::FilterMode;
+use iced_wgpu::graphics::Viewport;
 use iced_wgpu::primitive::{Pipeline, Primitive};
 use iced_wgpu::wgpu;
 use std::{
     collections::{BTreeMap, btree_map::Entry},
     num::NonZero,
+    pin::Pin,
     sync::{
-        Arc, Mutex,
+        Arc, Mutex, Weak,
         atomic::{AtomicBool, AtomicUsize, Ordering},
     },
+    task::{Context, Poll, Waker},
 };
 
 #[repr(C)]
 struct Uniforms {
     rect: [f32; 4],
+    /// Rotation and mirroring of `rect` around its center, as a column-major 2x2 matrix in normalized
+    /// device coordinates.
+    transform: [f32; 4],
+    /// Visible region of the frame in normalized texture coordinates (min x, min y, max x, max y).
+    uv_rect: [f32; 4],
+    /// Brightness, contrast, saturation and hue.
+    adjustments: [f32; 4],
+    /// Input domain of the LUT, with the blend strength in `w`.
+    lut_domain_min: [f32; 4],
+    /// Input domain of the LUT, with its size in `w`.
+    lut_domain_max: [f32; 4],
+    /// Visible bounds of the video in pixels of the render target, which are rounded and bordered.
+    frame_rect: [f32; 4],
+    /// Top left, top right, bottom right and bottom left corner radii in pixels.
+    radius: [f32; 4],
+    /// Straight (not premultiplied) alpha.
+    border_color: [f32; 4],
+    /// Region of the frame covered by bitmap subtitles in normalized texture coordinates, or empty.
+    subtitle_rect: [f32; 4],
+    gamma: f32,
+    filter_mode: u32,
+    border_width: f32,
+    opacity: f32,
     // because wgpu min_uniform_buffer_offset_alignment
-    _pad: [u8; 240],
+    _pad: [u8; 80],
+}
+
+#[repr(C)]
+struct EffectUniforms {
+    rect: [f32; 4],
+    params: [f32; MAX_EFFECT_PARAMS],
+    texel_size: [f32; 2],
+    // because wgpu min_uniform_buffer_offset_alignment
+    _pad: [u8; 168],
+}
+
+/// Number of instances of a video which can be drawn per frame before the instance buffer grows.
+const INITIAL_INSTANCES: usize = 16;
+
+/// Covers the whole render target, in normalized device coordinates.
+const FULL_RECT: [f32; 4] = [-1.0, 1.0, 1.0, -1.0];
+
+/// Picture adjustments applied after YUV conversion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Adjustments {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    /// Hue rotation in radians.
+    pub hue: f32,
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Adjustments {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+/// Per-widget drawing parameters of a [`VideoPrimitive`].
+#[derive(Debug, Clone)]
+pub(crate) struct RenderParams {
+    /// Visible region of the frame in normalized texture coordinates.
+    pub uv_rect: iced::Rectangle,
+    pub adjustments: Adjustments,
+    pub effects: Vec<EffectInstance>,
+    pub lut: Option<LutHandle>,
+    pub lut_strength: f32,
+    pub filter_mode: FilterMode,
+    /// Bounds of the video in logical pixels, before rotation and mirroring.
+    pub quad: iced::Rectangle,
+    pub rotation: iced::Radians,
+    pub mirror_horizontal: bool,
+    pub mirror_vertical: bool,
+    /// Visible bounds of the video in logical pixels, which are rounded and bordered.
+    pub frame: iced::Rectangle,
+    pub border: iced::Border,
+    pub opacity: f32,
+    /// Composite bitmap subtitles over the video.
+    pub subtitles: bool,
+}
+
+/// Intermediate textures and per-pass uniforms for drawing one video instance with effects.
+///
+/// The video is drawn into the first texture, then each effect reads from one texture and writes to the other,
+/// except for the last one which writes to the render target.
+struct EffectChain {
+    size: (u32, u32),
+    views: [wgpu::TextureView; 2],
+    uniforms: wgpu::Buffer,
+    bind_groups: Vec<wgpu::BindGroup>,
+    effects: Vec<u64>,
 }
 
 struct VideoEntry {
     texture_y: wgpu::Texture,
     texture_uv: wgpu::Texture,
+    /// Alpha plane, or a single opaque texel for videos without alpha.
+    texture_a: wgpu::Texture,
+    /// Bitmap subtitles, or a single transparent texel.
+    texture_subtitle: wgpu::Texture,
+    /// Views of the Y, UV, alpha and subtitle textures.
+    views: [wgpu::TextureView; 4],
+    /// Region of the frame covered by `texture_subtitle`, or empty without subtitles.
+    subtitle_rect: [f32; 4],
+    /// Uniforms of every instance of the video drawn this frame.
     instances: wgpu::Buffer,
+    /// The contents of `instances`, rewritten whenever it grows.
+    uniforms: Vec<Uniforms>,
+    /// Number of instances which fit in `instances`.
+    capacity: usize,
     bg0: wgpu::BindGroup,
     alive: Arc<AtomicBool>,
+    /// The Y, UV and alpha textures have mip levels, which are only created when mipmapping.
+    mipmapped: bool,
+    /// Some instance was mipmapped since the last frame was uploaded.
+    mips_used: bool,
+    /// Mip levels haven't been generated for the current frame yet.
+    mips_dirty: bool,
 
     prepare_index: AtomicUsize,
     render_index: AtomicUsize,
 }
 
+/// The validation errors of creating an effect pipeline, which are only `Send` on native backends.
+#[cfg(not(target_arch = "wasm32"))]
+type ErrorScope = Mutex<Pin<Box<dyn Future<Output = Option<wgpu::Error>> + Send>>>;
+#[cfg(target_arch = "wasm32")]
+type ErrorScope = Mutex<Pin<Box<dyn Future<Output = Option<wgpu::Error>>>>>;
+
+/// The pipeline of an effect, which is only drawn once it's known to be valid.
+enum EffectPipeline {
+    /// Waiting for validation, on backends reporting errors asynchronously (e.g., WebGPU).
+    Validating(wgpu::RenderPipeline, ErrorScope),
+    Valid(wgpu::RenderPipeline),
+    /// Failed to compile, remembered so the error is only logged once.
+    Invalid,
+}
+
 pub(crate) struct VideoPipeline {
     pipeline: wgpu::RenderPipeline,
     bg0_layout: wgpu::BindGroupLayout,
     sampler: wgpu::Sampler,
+    mip_layout: wgpu::BindGroupLayout,
+    /// Downsampling pipelines for the Y and UV planes.
+    mip_pipelines: [wgpu::RenderPipeline; 2],
     videos: BTreeMap<u64, VideoEntry>,
+    format: wgpu::TextureFormat,
+    effect_layout: wgpu::BindGroupLayout,
+    effect_pipeline_layout: wgpu::PipelineLayout,
+    /// Pipelines by the hash of the effect source.
+    effect_pipelines: BTreeMap<u64, EffectPipeline>,
+    /// Keyed by video ID and instance index.
+    effect_chains: BTreeMap<(u64, usize), EffectChain>,
+    lut_layout: wgpu::BindGroupLayout,
+    /// Bound when no LUT is used, so the shader doesn't need to branch.
+    identity_lut: wgpu::BindGroup,
+    luts: BTreeMap<u64, (Weak<[u32]>, wgpu::BindGroup)>,
+    /// LUT used by each instance, keyed by video ID and instance index.
+    instance_luts: BTreeMap<(u64, usize), u64>,
 }
 
 impl Pipeline for VideoPipeline {
-    fn new(device: &wgpu::Device, _queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("iced_video_player shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
@@ -73,7 +226,7 @@ impl Pipeline for VideoPipeline {
                 },
                 wgpu::BindGroupLayoutEntry {
                     binding: 3,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: true,
@@ -81,12 +234,54 @@ impl Pipeline for VideoPipeline {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let lut_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("iced_video_player LUT bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
             ],
         });
 
         let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("iced_video_player pipeline layout"),
-            bind_group_layouts: &[&bg0_layout],
+            bind_group_layouts: &[&bg0_layout, &lut_layout],
             push_constant_ranges: &[],
         });
 
@@ -111,7 +306,7 @@ impl Pipeline for VideoPipeline {
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
-                    blend: None,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: Default::default(),
@@ -127,19 +322,137 @@ impl Pipeline for VideoPipeline {
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
             min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Linear,
             lod_min_clamp: 0.0,
-            lod_max_clamp: 1.0,
+            lod_max_clamp: 32.0,
             compare: None,
             anisotropy_clamp: 1,
             border_color: None,
         });
 
+        let mip_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("iced_video_player mip shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("mip.wgsl").into()),
+        });
+
+        let mip_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("iced_video_player mip bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let mip_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("iced_video_player mip pipeline layout"),
+            bind_group_layouts: &[&mip_layout],
+            push_constant_ranges: &[],
+        });
+
+        let mip_pipelines =
+            [wgpu::TextureFormat::R8Unorm, wgpu::TextureFormat::Rg8Unorm].map(|format| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("iced_video_player mip pipeline"),
+                    layout: Some(&mip_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &mip_shader,
+                        entry_point: Some("vs_main"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &mip_shader,
+                        entry_point: Some("fs_main"),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    multiview: None,
+                    cache: None,
+                })
+            });
+
+        let effect_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("iced_video_player effect bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let effect_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("iced_video_player effect pipeline layout"),
+                bind_group_layouts: &[&effect_layout],
+                push_constant_ranges: &[],
+            });
+
+        // 2x2x2 LUT mapping every color to itself, red varying fastest
+        let identity: Vec<u32> = (0..8u32)
+            .map(|i| lut::pack_rgb10a2((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2) as f32))
+            .collect();
+        let identity_lut = create_lut(device, queue, &lut_layout, &sampler, 2, &identity);
+
         VideoPipeline {
             pipeline,
             bg0_layout,
             sampler,
+            mip_layout,
+            mip_pipelines,
             videos: BTreeMap::new(),
+            format,
+            effect_layout,
+            effect_pipeline_layout,
+            effect_pipelines: BTreeMap::new(),
+            effect_chains: BTreeMap::new(),
+            lut_layout,
+            identity_lut,
+            luts: BTreeMap::new(),
+            instance_luts: BTreeMap::new(),
         }
     }
 
@@ -153,9 +466,23 @@ impl Pipeline for VideoPipeline {
             if let Some(video) = self.videos.remove(&id) {
                 video.texture_y.destroy();
                 video.texture_uv.destroy();
+                video.texture_a.destroy();
+                video.texture_subtitle.destroy();
                 video.instances.destroy();
             }
         }
+        self.effect_chains
+            .retain(|(id, _), _| self.videos.contains_key(id));
+        self.effect_pipelines.retain(|id, pipeline| {
+            matches!(pipeline, EffectPipeline::Invalid)
+                || self
+                    .effect_chains
+                    .values()
+                    .any(|chain| chain.effects.contains(id))
+        });
+        self.instance_luts
+            .retain(|(id, _), _| self.videos.contains_key(id));
+        self.luts.retain(|_, (texels, _)| texels.strong_count() > 0);
     }
 }
 
@@ -169,115 +496,114 @@ impl VideoPipeline {
         (width, height): (u32, u32),
         frame: &[u8],
         stride: Option<u32>,
+        alpha_offset: Option<usize>,
+        mipmapped: bool,
     ) {
         // Use stride from GStreamer's VideoMeta if available, otherwise assume stride == width
         let stride = stride.unwrap_or(width);
-        if let Entry::Vacant(entry) = self.videos.entry(video_id) {
-            let texture_y = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("iced_video_player texture"),
-                size: wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::R8Unorm,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-
-            let texture_uv = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("iced_video_player texture"),
-                size: wgpu::Extent3d {
-                    width: width / 2,
-                    height: height / 2,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rg8Unorm,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
+        // mip levels are kept while any instance used them since the last frame, so videos drawn
+        // with different filters don't recreate their textures every frame
+        let mipmapped = mipmapped
+            || self
+                .videos
+                .get_mut(&video_id)
+                .is_some_and(|video| std::mem::take(&mut video.mips_used));
 
-            let view_y = texture_y.create_view(&wgpu::TextureViewDescriptor {
-                label: Some("iced_video_player texture view"),
-                format: None,
-                dimension: None,
-                aspect: wgpu::TextureAspect::All,
-                base_mip_level: 0,
-                mip_level_count: None,
-                base_array_layer: 0,
-                array_layer_count: None,
-                usage: None,
-            });
+        if let Entry::Vacant(entry) = self.videos.entry(video_id) {
+            let [texture_y, texture_uv, texture_a] = create_plane_textures(
+                device,
+                queue,
+                (width, height),
+                alpha_offset.is_some(),
+                mipmapped,
+            );
 
-            let view_uv = texture_uv.create_view(&wgpu::TextureViewDescriptor {
-                label: Some("iced_video_player texture view"),
-                format: None,
-                dimension: None,
-                aspect: wgpu::TextureAspect::All,
-                base_mip_level: 0,
-                mip_level_count: None,
-                base_array_layer: 0,
-                array_layer_count: None,
-                usage: None,
-            });
+            let texture_subtitle = create_subtitle_texture(device, 1, 1);
+            queue.write_texture(
+                texture_subtitle.as_image_copy(),
+                &[0; 4],
+                wgpu::TexelCopyBufferLayout::default(),
+                wgpu::Extent3d::default(),
+            );
 
-            let instances = device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("iced_video_player uniform buffer"),
-                size: 256 * std::mem::size_of::<Uniforms>() as u64, // max 256 video players per frame
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
-                mapped_at_creation: false,
+            let views = [&texture_y, &texture_uv, &texture_a, &texture_subtitle].map(|texture| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("iced_video_player texture view"),
+                    ..Default::default()
+                })
             });
 
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("iced_video_player bind group"),
-                layout: &self.bg0_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&view_y),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&view_uv),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::Sampler(&self.sampler),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &instances,
-                            offset: 0,
-                            size: Some(NonZero::new(std::mem::size_of::<Uniforms>() as _).unwrap()),
-                        }),
-                    },
-                ],
-            });
+            let instances = create_instance_buffer(device, INITIAL_INSTANCES);
+            let bind_group = create_video_bind_group(
+                device,
+                &self.bg0_layout,
+                &self.sampler,
+                &views,
+                &instances,
+            );
 
             entry.insert(VideoEntry {
                 texture_y,
                 texture_uv,
+                texture_a,
+                texture_subtitle,
+                views,
+                subtitle_rect: [0.0; 4],
                 instances,
+                uniforms: Vec::new(),
+                capacity: INITIAL_INSTANCES,
                 bg0: bind_group,
                 alive: Arc::clone(alive),
+                mipmapped,
+                mips_used: false,
+                mips_dirty: true,
 
                 prepare_index: AtomicUsize::new(0),
                 render_index: AtomicUsize::new(0),
             });
         }
 
+        let video = self.videos.get_mut(&video_id).unwrap();
+        if video.mipmapped != mipmapped {
+            for texture in [&video.texture_y, &video.texture_uv, &video.texture_a] {
+                texture.destroy();
+            }
+            [video.texture_y, video.texture_uv, video.texture_a] = create_plane_textures(
+                device,
+                queue,
+                (width, height),
+                alpha_offset.is_some(),
+                mipmapped,
+            );
+            for (view, texture) in
+                video
+                    .views
+                    .iter_mut()
+                    .zip([&video.texture_y, &video.texture_uv, &video.texture_a])
+            {
+                *view = texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("iced_video_player texture view"),
+                    ..Default::default()
+                });
+            }
+            video.bg0 = create_video_bind_group(
+                device,
+                &self.bg0_layout,
+                &self.sampler,
+                &video.views,
+                &video.instances,
+            );
+            video.mipmapped = mipmapped;
+        }
+
         let VideoEntry {
             texture_y,
             texture_uv,
+            texture_a,
+            mips_dirty,
             ..
-        } = self.videos.get(&video_id).unwrap();
+        } = video;
+        *mips_dirty = true;
 
         queue.write_texture(
             wgpu::TexelCopyTextureInfo {
@@ -318,32 +644,512 @@ impl VideoPipeline {
                 depth_or_array_layers: 1,
             },
         );
+
+        if let Some(offset) = alpha_offset {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: texture_a,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &frame[offset..],
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(stride),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    /// Whether a video is drawn with `mipmapped` filtering, but its textures lack mip levels.
+    fn lacks_mips(&self, video_id: u64, mipmapped: bool) -> bool {
+        mipmapped
+            && self
+                .videos
+                .get(&video_id)
+                .is_some_and(|video| !video.mipmapped)
+    }
+
+    /// Replaces the bitmap subtitles of a video, or removes them if `bitmap` is `None`.
+    fn upload_subtitle(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        video_id: u64,
+        bitmap: Option<&Bitmap>,
+    ) {
+        let Some(video) = self.videos.get_mut(&video_id) else {
+            return;
+        };
+        let Some(bitmap) = bitmap.filter(|bitmap| bitmap.width > 0 && bitmap.height > 0) else {
+            video.subtitle_rect = [0.0; 4];
+            return;
+        };
+
+        let size = wgpu::Extent3d {
+            width: bitmap.width,
+            height: bitmap.height,
+            depth_or_array_layers: 1,
+        };
+        if video.texture_subtitle.size() != size {
+            video.texture_subtitle.destroy();
+            video.texture_subtitle = create_subtitle_texture(device, bitmap.width, bitmap.height);
+            video.views[3] = video
+                .texture_subtitle
+                .create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("iced_video_player texture view"),
+                    ..Default::default()
+                });
+            video.bg0 = create_video_bind_group(
+                device,
+                &self.bg0_layout,
+                &self.sampler,
+                &video.views,
+                &video.instances,
+            );
+        }
+
+        queue.write_texture(
+            video.texture_subtitle.as_image_copy(),
+            &bitmap.pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bitmap.width * 4),
+                rows_per_image: Some(bitmap.height),
+            },
+            size,
+        );
+        video.subtitle_rect = [
+            bitmap.rect.x,
+            bitmap.rect.y,
+            bitmap.rect.x + bitmap.rect.width,
+            bitmap.rect.y + bitmap.rect.height,
+        ];
     }
 
-    fn prepare(&mut self, queue: &wgpu::Queue, video_id: u64, bounds: &iced::Rectangle) {
-        if let Some(video) = self.videos.get_mut(&video_id) {
-            let uniforms = Uniforms {
-                rect: [
-                    bounds.x,
-                    bounds.y,
-                    bounds.x + bounds.width,
-                    bounds.y + bounds.height,
+    fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        video_id: u64,
+        bounds: &iced::Rectangle,
+        viewport: &Viewport,
+        params: &RenderParams,
+    ) {
+        let logical_size = viewport.logical_size();
+        let ndc = *bounds
+            * iced::Transformation::orthographic(logical_size.width as _, logical_size.height as _);
+        let rect = [ndc.x, ndc.y, ndc.x + ndc.width, ndc.y + ndc.height];
+
+        // effects which failed to compile, or aren't validated yet, are skipped
+        let effects: Vec<_> = params
+            .effects
+            .iter()
+            .filter(|instance| self.prepare_effect_pipeline(device, instance))
+            .cloned()
+            .collect();
+
+        // with effects, the video is drawn into an intermediate texture covering `bounds` first
+        let (target_size, quad) = if effects.is_empty() {
+            (logical_size, params.quad)
+        } else {
+            (
+                bounds.size(),
+                iced::Rectangle {
+                    x: params.quad.x - bounds.x,
+                    y: params.quad.y - bounds.y,
+                    ..params.quad
+                },
+            )
+        };
+        let quad = quad
+            * iced::Transformation::orthographic(target_size.width as _, target_size.height as _);
+
+        // rotating in pixels, then converting back to normalized device coordinates,
+        // which are scaled differently along each axis and have y pointing up
+        let (sin, cos) = params.rotation.0.sin_cos();
+        let aspect = target_size.width / target_size.height.max(1.0);
+        let mirror_x = if params.mirror_horizontal { -1.0 } else { 1.0 };
+        let mirror_y = if params.mirror_vertical { -1.0 } else { 1.0 };
+        let transform = [
+            cos * mirror_x,
+            -sin * aspect * mirror_x,
+            sin / aspect * mirror_y,
+            cos * mirror_y,
+        ];
+
+        let scale = viewport.scale_factor();
+        let physical_bounds = *bounds * scale;
+        let max = device.limits().max_texture_dimension_2d;
+        let chain_size = (
+            (physical_bounds.width.round() as u32).clamp(1, max),
+            (physical_bounds.height.round() as u32).clamp(1, max),
+        );
+
+        // rounding and borders are computed in pixels of the texture the video is drawn into
+        let (frame, scale) = if effects.is_empty() {
+            (params.frame * scale, scale)
+        } else {
+            let texture_scale = chain_size.0 as f32 / bounds.width.max(1.0);
+            (
+                iced::Rectangle {
+                    x: params.frame.x - bounds.x,
+                    y: params.frame.y - bounds.y,
+                    ..params.frame
+                } * texture_scale,
+                texture_scale,
+            )
+        };
+        let radius: [f32; 4] = params.border.radius.into();
+
+        let Some(video) = self.videos.get_mut(&video_id) else {
+            return;
+        };
+        let index = video.prepare_index.load(Ordering::Relaxed);
+        let adjustments = &params.adjustments;
+        let uniforms = Uniforms {
+            rect: [quad.x, quad.y, quad.x + quad.width, quad.y + quad.height],
+            transform,
+            uv_rect: [
+                params.uv_rect.x,
+                params.uv_rect.y,
+                params.uv_rect.x + params.uv_rect.width,
+                params.uv_rect.y + params.uv_rect.height,
+            ],
+            adjustments: [
+                adjustments.brightness,
+                adjustments.contrast,
+                adjustments.saturation,
+                adjustments.hue,
+            ],
+            lut_domain_min: match &params.lut {
+                Some(lut) => [
+                    lut.domain_min[0],
+                    lut.domain_min[1],
+                    lut.domain_min[2],
+                    params.lut_strength.clamp(0.0, 1.0),
                 ],
-                _pad: [0; 240],
-            };
+                None => [0.0; 4],
+            },
+            lut_domain_max: match &params.lut {
+                Some(lut) => [
+                    lut.domain_max[0],
+                    lut.domain_max[1],
+                    lut.domain_max[2],
+                    lut.size as f32,
+                ],
+                None => [1.0, 1.0, 1.0, 2.0],
+            },
+            frame_rect: [
+                frame.x,
+                frame.y,
+                frame.x + frame.width,
+                frame.y + frame.height,
+            ],
+            radius: radius.map(|radius| radius * scale),
+            border_color: iced_wgpu::graphics::color::pack(params.border.color).components(),
+            subtitle_rect: if params.subtitles {
+                video.subtitle_rect
+            } else {
+                [0.0; 4]
+            },
+            gamma: adjustments.gamma.max(0.01),
+            filter_mode: match params.filter_mode {
+                FilterMode::Nearest => 0,
+                FilterMode::Bilinear => 1,
+                FilterMode::Bicubic => 2,
+                FilterMode::Lanczos => 3,
+                FilterMode::Mipmapped => 4,
+            },
+            border_width: params.border.width * scale,
+            opacity: params.opacity.clamp(0.0, 1.0),
+            _pad: [0; 80],
+        };
+        video.uniforms.truncate(index);
+        video.uniforms.push(uniforms);
+        if index >= video.capacity {
+            // the instances already prepared this frame are written again, as copying them would need
+            // a submission of its own before the frame's
+            let capacity = (index + 1).next_power_of_two();
+            video.instances = create_instance_buffer(device, capacity);
+            video.bg0 = create_video_bind_group(
+                device,
+                &self.bg0_layout,
+                &self.sampler,
+                &video.views,
+                &video.instances,
+            );
+            video.capacity = capacity;
+            queue.write_buffer(&video.instances, 0, uniform_bytes(&video.uniforms));
+        } else {
             queue.write_buffer(
                 &video.instances,
-                (video.prepare_index.load(Ordering::Relaxed) * std::mem::size_of::<Uniforms>())
-                    as u64,
+                (index * std::mem::size_of::<Uniforms>()) as u64,
+                uniform_bytes(&video.uniforms[index..]),
+            );
+        }
+        video.prepare_index.fetch_add(1, Ordering::Relaxed);
+        video.render_index.store(0, Ordering::Relaxed);
+
+        if params.filter_mode == FilterMode::Mipmapped {
+            video.mips_used = true;
+        }
+        if params.filter_mode == FilterMode::Mipmapped && video.mipmapped && video.mips_dirty {
+            video.mips_dirty = false;
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("iced_video_player mip encoder"),
+            });
+            // the alpha plane is single channel, like the Y plane
+            for (texture, pipeline) in [&video.texture_y, &video.texture_uv, &video.texture_a]
+                .into_iter()
+                .zip(self.mip_pipelines.iter().chain([&self.mip_pipelines[0]]))
+            {
+                generate_mips(
+                    device,
+                    &mut encoder,
+                    &self.mip_layout,
+                    &self.sampler,
+                    pipeline,
+                    texture,
+                );
+            }
+            queue.submit(Some(encoder.finish()));
+        }
+
+        match &params.lut {
+            Some(lut) => {
+                if !self.luts.contains_key(&lut.id) {
+                    let bind_group = create_lut(
+                        device,
+                        queue,
+                        &self.lut_layout,
+                        &self.sampler,
+                        lut.size,
+                        &lut.texels,
+                    );
+                    self.luts
+                        .insert(lut.id, (Arc::downgrade(&lut.texels), bind_group));
+                }
+                self.instance_luts.insert((video_id, index), lut.id);
+            }
+            None => {
+                self.instance_luts.remove(&(video_id, index));
+            }
+        }
+
+        if effects.is_empty() {
+            self.effect_chains.remove(&(video_id, index));
+        } else {
+            self.prepare_effects(device, queue, (video_id, index), rect, chain_size, &effects);
+        }
+    }
+
+    fn prepare_effects(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        key: (u64, usize),
+        rect: [f32; 4],
+        size: (u32, u32),
+        effects: &[EffectInstance],
+    ) {
+        let outdated = self
+            .effect_chains
+            .get(&key)
+            .is_none_or(|chain| chain.size != size || chain.bind_groups.len() != effects.len());
+        if outdated {
+            let chain = self.create_effect_chain(device, size, effects.len());
+            self.effect_chains.insert(key, chain);
+        }
+        let chain = self.effect_chains.get_mut(&key).unwrap();
+
+        chain.effects = effects.iter().map(|instance| instance.effect.id).collect();
+
+        for (i, instance) in effects.iter().enumerate() {
+            let uniforms = EffectUniforms {
+                rect: if i + 1 == effects.len() {
+                    rect
+                } else {
+                    FULL_RECT
+                },
+                params: instance.params,
+                texel_size: [1.0 / size.0 as f32, 1.0 / size.1 as f32],
+                _pad: [0; 168],
+            };
+            queue.write_buffer(
+                &chain.uniforms,
+                (i * std::mem::size_of::<EffectUniforms>()) as u64,
                 unsafe {
                     std::slice::from_raw_parts(
                         &uniforms as *const _ as *const u8,
-                        std::mem::size_of::<Uniforms>(),
+                        std::mem::size_of::<EffectUniforms>(),
                     )
                 },
             );
-            video.prepare_index.fetch_add(1, Ordering::Relaxed);
-            video.render_index.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Compiles the pipeline of an effect the first time it's drawn, returning whether it's known
+    /// to be valid, so it can be drawn.
+    fn prepare_effect_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        instance: &EffectInstance,
+    ) -> bool {
+        let id = instance.effect.id;
+        let pipeline = match self.effect_pipelines.remove(&id) {
+            Some(pipeline) => pipeline,
+            None => {
+                device.push_error_scope(wgpu::ErrorFilter::Validation);
+                let pipeline = self.create_effect_pipeline(device, instance);
+                let scope: ErrorScope = Mutex::new(Box::pin(device.pop_error_scope()));
+                EffectPipeline::Validating(pipeline, scope)
+            }
+        };
+
+        // native backends report errors right away, so effects are usually valid on the first frame,
+        // and there's no need to block on the future
+        let pipeline = match pipeline {
+            EffectPipeline::Validating(pipeline, mut scope) => {
+                let error = scope
+                    .get_mut()
+                    .expect("lock effect error scope")
+                    .as_mut()
+                    .poll(&mut Context::from_waker(Waker::noop()));
+                match error {
+                    Poll::Ready(Some(error)) => {
+                        log::error!("invalid effect {:?}: {error}", instance.effect.label);
+                        EffectPipeline::Invalid
+                    }
+                    Poll::Ready(None) => EffectPipeline::Valid(pipeline),
+                    Poll::Pending => EffectPipeline::Validating(pipeline, scope),
+                }
+            }
+            pipeline => pipeline,
+        };
+
+        let valid = matches!(pipeline, EffectPipeline::Valid(_));
+        self.effect_pipelines.insert(id, pipeline);
+        valid
+    }
+
+    fn create_effect_pipeline(
+        &self,
+        device: &wgpu::Device,
+        instance: &EffectInstance,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&instance.effect.label),
+            source: wgpu::ShaderSource::Wgsl(instance.effect.module_source().into()),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&instance.effect.label),
+            layout: Some(&self.effect_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("effect_vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("effect_fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    fn create_effect_chain(
+        &self,
+        device: &wgpu::Device,
+        (width, height): (u32, u32),
+        passes: usize,
+    ) -> EffectChain {
+        let views = [(); 2].map(|_| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("iced_video_player effect texture"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("iced_video_player effect uniform buffer"),
+            size: (passes * std::mem::size_of::<EffectUniforms>()) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+
+        let bind_groups = (0..passes)
+            .map(|i| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("iced_video_player effect bind group"),
+                    layout: &self.effect_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&views[i % 2]),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                                buffer: &uniforms,
+                                offset: (i * std::mem::size_of::<EffectUniforms>()) as u64,
+                                size: Some(
+                                    NonZero::new(std::mem::size_of::<EffectUniforms>() as _)
+                                        .unwrap(),
+                                ),
+                            }),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        EffectChain {
+            size: (width, height),
+            views,
+            uniforms,
+            bind_groups,
+            effects: Vec::new(),
         }
     }
 
@@ -355,13 +1161,95 @@ impl VideoPipeline {
         video_id: u64,
     ) {
         if let Some(video) = self.videos.get(&video_id) {
+            let index = video.render_index.load(Ordering::Relaxed);
+            if index >= video.capacity {
+                log::error!("video {video_id} drawn more times than it was prepared");
+                return;
+            }
+            let chain = self.effect_chains.get(&(video_id, index));
+
+            {
+                let (view, load) = match chain {
+                    Some(chain) => (
+                        &chain.views[0],
+                        wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    ),
+                    None => (target, wgpu::LoadOp::Load),
+                };
+
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("iced_video_player render pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(
+                    0,
+                    &video.bg0,
+                    &[(index * std::mem::size_of::<Uniforms>()) as u32],
+                );
+                let lut = self
+                    .instance_luts
+                    .get(&(video_id, index))
+                    .and_then(|id| self.luts.get(id))
+                    .map_or(&self.identity_lut, |(_, bind_group)| bind_group);
+                pass.set_bind_group(1, lut, &[]);
+                if chain.is_none() {
+                    pass.set_scissor_rect(
+                        clip.x as _,
+                        clip.y as _,
+                        clip.width as _,
+                        clip.height as _,
+                    );
+                }
+                pass.draw(0..6, 0..1);
+            }
+
+            if let Some(chain) = chain {
+                self.draw_effects(chain, target, encoder, clip);
+            }
+
+            video.prepare_index.store(0, Ordering::Relaxed);
+            video.render_index.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn draw_effects(
+        &self,
+        chain: &EffectChain,
+        target: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        clip: &iced::Rectangle<u32>,
+    ) {
+        for (i, id) in chain.effects.iter().enumerate() {
+            let last = i + 1 == chain.effects.len();
+            let (view, load) = if last {
+                (target, wgpu::LoadOp::Load)
+            } else {
+                (
+                    &chain.views[(i + 1) % 2],
+                    wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                )
+            };
+
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("iced_video_player render pass"),
+                label: Some("iced_video_player effect pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: target,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
+                        load,
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
@@ -371,31 +1259,281 @@ impl VideoPipeline {
                 occlusion_query_set: None,
             });
 
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(
-                0,
-                &video.bg0,
-                &[
-                    (video.render_index.load(Ordering::Relaxed) * std::mem::size_of::<Uniforms>())
-                        as u32,
-                ],
-            );
-            pass.set_scissor_rect(clip.x as _, clip.y as _, clip.width as _, clip.height as _);
+            let Some(EffectPipeline::Valid(pipeline)) = self.effect_pipelines.get(id) else {
+                continue;
+            };
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &chain.bind_groups[i], &[]);
+            if last {
+                pass.set_scissor_rect(clip.x as _, clip.y as _, clip.width as _, clip.height as _);
+            }
             pass.draw(0..6, 0..1);
-
-            video.prepare_index.store(0, Ordering::Relaxed);
-            video.render_index.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
 
+/// Number of mip levels down to 1x1.
+fn mip_level_count(width: u32, height: u32) -> u32 {
+    width.max(height).max(1).ilog2() + 1
+}
+
+/// Creates the Y, UV and alpha textures of a video, with mip levels to render into if `mipmapped`.
+///
+/// Videos without alpha get a single opaque texel instead.
+fn create_plane_textures(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    (width, height): (u32, u32),
+    alpha: bool,
+    mipmapped: bool,
+) -> [wgpu::Texture; 3] {
+    let alpha_size = if alpha { (width, height) } else { (1, 1) };
+    let [texture_y, texture_uv, texture_a] = [
+        ((width, height), wgpu::TextureFormat::R8Unorm),
+        ((width / 2, height / 2), wgpu::TextureFormat::Rg8Unorm),
+        (alpha_size, wgpu::TextureFormat::R8Unorm),
+    ]
+    .map(|((width, height), format)| {
+        let (mip_level_count, usage) = if mipmapped {
+            (
+                mip_level_count(width, height),
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        } else {
+            (1, wgpu::TextureUsages::empty())
+        };
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("iced_video_player texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING | usage,
+            view_formats: &[],
+        })
+    });
+
+    if !alpha {
+        queue.write_texture(
+            texture_a.as_image_copy(),
+            &[0xFF],
+            wgpu::TexelCopyBufferLayout::default(),
+            wgpu::Extent3d::default(),
+        );
+    }
+
+    [texture_y, texture_uv, texture_a]
+}
+
+/// Creates an RGBA texture for bitmap subtitles, whose pixels are premultiplied.
+fn create_subtitle_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("iced_video_player subtitle texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("iced_video_player uniform buffer"),
+        size: (capacity * std::mem::size_of::<Uniforms>()) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+        mapped_at_creation: false,
+    })
+}
+
+fn uniform_bytes(uniforms: &[Uniforms]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(
+            uniforms.as_ptr() as *const u8,
+            std::mem::size_of_val(uniforms),
+        )
+    }
+}
+
+/// `views` are the Y, UV, alpha and subtitle textures.
+fn create_video_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    [view_y, view_uv, view_a, view_subtitle]: &[wgpu::TextureView; 4],
+    instances: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("iced_video_player bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view_y),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(view_uv),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: instances,
+                    offset: 0,
+                    size: Some(NonZero::new(std::mem::size_of::<Uniforms>() as _).unwrap()),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(view_a),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(view_subtitle),
+            },
+        ],
+    })
+}
+
+/// Fills every mip level of `texture` from the level above it.
+fn generate_mips(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    pipeline: &wgpu::RenderPipeline,
+    texture: &wgpu::Texture,
+) {
+    let level_view = |level| {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("iced_video_player mip view"),
+            base_mip_level: level,
+            mip_level_count: Some(1),
+            ..Default::default()
+        })
+    };
+
+    for level in 1..texture.mip_level_count() {
+        let source = level_view(level - 1);
+        let target = level_view(level);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("iced_video_player mip bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("iced_video_player mip pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Uploads a `size`^3 LUT of `Rgb10a2Unorm` texels.
+fn create_lut(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    size: u32,
+    texels: &[u32],
+) -> wgpu::BindGroup {
+    let extent = wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: size,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("iced_video_player LUT texture"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: wgpu::TextureFormat::Rgb10a2Unorm,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        texture.as_image_copy(),
+        unsafe { std::slice::from_raw_parts(texels.as_ptr() as *const u8, texels.len() * 4) },
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(size * 4),
+            rows_per_image: Some(size),
+        },
+        extent,
+    );
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("iced_video_player LUT bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct VideoPrimitive {
     video_id: u64,
     alive: Arc<AtomicBool>,
     frame: Arc<Mutex<Frame>>,
     size: (u32, u32),
+    alpha: bool,
+    params: RenderParams,
     upload_frame: bool,
+    /// Bitmap subtitles to upload, if they changed since the last frame.
+    subtitle: Option<Arc<Mutex<Option<Bitmap>>>>,
 }
 
 impl VideoPrimitive {
@@ -404,14 +1542,20 @@ impl VideoPrimitive {
         alive: Arc<AtomicBool>,
         frame: Arc<Mutex<Frame>>,
         size: (u32, u32),
+        alpha: bool,
+        params: RenderParams,
         upload_frame: bool,
+        subtitle: Option<Arc<Mutex<Option<Bitmap>>>>,
     ) -> Self {
         VideoPrimitive {
             video_id,
             alive,
             frame,
             size,
+            alpha,
+            params,
             upload_frame,
+            subtitle,
         }
     }
 }
@@ -427,9 +1571,14 @@ impl Primitive for VideoPrimitive {
         bounds: &iced::Rectangle,
         viewport: &iced_wgpu::graphics::Viewport,
     ) {
-        if self.upload_frame {
+        // switching to mipmapping recreates the textures with mip levels, so the frame is needed again
+        let mipmapped = self.params.filter_mode == FilterMode::Mipmapped;
+        if self.upload_frame || pipeline.lacks_mips(self.video_id, mipmapped) {
             let frame_guard = self.frame.lock().expect("lock frame mutex");
             let stride = frame_guard.stride();
+            let alpha_offset = self
+                .alpha
+                .then(|| frame_guard.alpha_offset(self.size.0, self.size.1));
             if let Some(readable) = frame_guard.readable() {
                 pipeline.upload(
                     device,
@@ -439,19 +1588,18 @@ impl Primitive for VideoPrimitive {
                     self.size,
                     readable.as_slice(),
                     stride,
+                    alpha_offset,
+                    mipmapped,
                 );
             };
         }
 
-        pipeline.prepare(
-            queue,
-            self.video_id,
-            &(*bounds
-                * iced::Transformation::orthographic(
-                    viewport.logical_size().width as _,
-                    viewport.logical_size().height as _,
-                )),
-        );
+        if let Some(subtitle) = &self.subtitle {
+            let bitmap = subtitle.lock().expect("lock subtitle bitmap");
+            pipeline.upload_subtitle(device, queue, self.video_id, bitmap.as_ref());
+        }
+
+        pipeline.prepare(device, queue, self.video_id, bounds, viewport, &self.params);
     }
 
     fn render(
@@ -464,3 +1612,137 @@ impl Primitive for VideoPrimitive {
         pipeline.draw(target, encoder, clip_bounds, self.video_id);
     }
 }
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::effect::{Effect, MAX_EFFECT_PARAMS};
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Creates a device and the pipeline, or `None` on machines without any wgpu adapter.
+    fn setup() -> Option<(wgpu::Device, wgpu::Queue, VideoPipeline)> {
+        let instance = wgpu::Instance::default();
+        let adapter = match pollster::block_on(
+            instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+        ) {
+            Ok(adapter) => adapter,
+            Err(err) => {
+                eprintln!("skipping pipeline test: {err}");
+                return None;
+            }
+        };
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+        let pipeline = VideoPipeline::new(&device, &queue, FORMAT);
+        Some((device, queue, pipeline))
+    }
+
+    #[test]
+    fn grows_instances() {
+        let Some((device, queue, mut pipeline)) = setup() else {
+            return;
+        };
+        let alive = Arc::new(AtomicBool::new(true));
+        // 4x4 NV12 frame
+        pipeline.upload(
+            &device,
+            &queue,
+            0,
+            &alive,
+            (4, 4),
+            &[128; 24],
+            None,
+            None,
+            false,
+        );
+
+        let bounds = iced::Rectangle::new(iced::Point::ORIGIN, iced::Size::new(64.0, 64.0));
+        let viewport = Viewport::with_physical_size(iced::Size::new(64, 64), 1.0);
+        let params = RenderParams {
+            uv_rect: iced::Rectangle::new(iced::Point::ORIGIN, iced::Size::new(1.0, 1.0)),
+            adjustments: Adjustments::default(),
+            effects: Vec::new(),
+            lut: None,
+            lut_strength: 1.0,
+            filter_mode: FilterMode::Bilinear,
+            quad: bounds,
+            rotation: iced::Radians(0.0),
+            mirror_horizontal: false,
+            mirror_vertical: false,
+            frame: bounds,
+            border: iced::Border::default(),
+            opacity: 1.0,
+            subtitles: false,
+        };
+        let instances = INITIAL_INSTANCES + 1;
+        for _ in 0..instances {
+            pipeline.prepare(&device, &queue, 0, &bounds, &viewport, &params);
+        }
+        let video = &pipeline.videos[&0];
+        assert_eq!(video.capacity, 2 * INITIAL_INSTANCES);
+        assert_eq!(video.uniforms.len(), instances);
+
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 64,
+                height: 64,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let clip = iced::Rectangle {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64,
+        };
+
+        // drawing more instances than fit in the buffer is skipped, rather than binding out of bounds
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        for _ in 0..=video.capacity {
+            pipeline.draw(&view, &mut encoder, &clip, 0);
+        }
+        queue.submit(Some(encoder.finish()));
+        let error = pollster::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+    }
+
+    #[test]
+    fn skips_invalid_effects() {
+        let Some((device, _queue, mut pipeline)) = setup() else {
+            return;
+        };
+
+        let effect = |source: &str| EffectInstance {
+            effect: Effect::new("test", source),
+            params: [0.0; MAX_EFFECT_PARAMS],
+        };
+        let valid =
+            effect("fn effect(color: vec4<f32>, uv: vec2<f32>) -> vec4<f32> { return color; }");
+        let invalid = effect("fn effect(color: vec4<f32>) -> f32 { return color; }");
+
+        // errors may be reported asynchronously, so give validation a few frames
+        let mut validate = |instance: &EffectInstance| {
+            (0..100).any(|_| {
+                let valid = pipeline.prepare_effect_pipeline(&device, instance);
+                let _ = device.poll(wgpu::PollType::Poll);
+                valid
+            })
+        };
+        assert!(validate(&valid));
+        assert!(!validate(&invalid));
+        assert!(matches!(
+            pipeline.effect_pipelines.get(&invalid.effect.id),
+            Some(EffectPipeline::Invalid)
+        ));
+    }
+}
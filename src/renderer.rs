@@ -0,0 +1,56 @@
+use iced::advanced::{image, text};
+use iced_wgpu::primitive::{Primitive, Renderer as PrimitiveRenderer};
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for iced_wgpu::Renderer {}
+    impl Sealed for iced_tiny_skia::Renderer {}
+    impl<A, B> Sealed for iced_renderer::fallback::Renderer<A, B> {}
+}
+
+/// A renderer which can draw a [`VideoPlayer`](crate::VideoPlayer).
+///
+/// Renderers supporting custom `wgpu` primitives upload frames to the GPU and convert them there.
+/// Any other renderer (e.g., `tiny-skia` on machines without a GPU) falls back to converting frames
+/// to RGBA on the CPU and drawing them as images, which is considerably slower.
+///
+/// This trait is sealed: it's implemented for `iced_wgpu`, `iced_tiny_skia` and iced's default
+/// fallback renderer combining them, and can't be implemented outside of this crate.
+pub trait Renderer:
+    private::Sealed + image::Renderer<Handle = image::Handle> + text::Renderer<Font = iced::Font>
+{
+    /// Draws a custom primitive, returning `false` if primitives aren't supported by this renderer.
+    #[doc(hidden)]
+    fn draw_video_primitive(&mut self, bounds: iced::Rectangle, primitive: impl Primitive) -> bool;
+}
+
+impl Renderer for iced_wgpu::Renderer {
+    fn draw_video_primitive(&mut self, bounds: iced::Rectangle, primitive: impl Primitive) -> bool {
+        self.draw_primitive(bounds, primitive);
+        true
+    }
+}
+
+impl Renderer for iced_tiny_skia::Renderer {
+    fn draw_video_primitive(
+        &mut self,
+        _bounds: iced::Rectangle,
+        _primitive: impl Primitive,
+    ) -> bool {
+        false
+    }
+}
+
+impl<A, B> Renderer for iced_renderer::fallback::Renderer<A, B>
+where
+    A: Renderer,
+    B: Renderer + text::Renderer<Paragraph = A::Paragraph, Editor = A::Editor>,
+{
+    fn draw_video_primitive(&mut self, bounds: iced::Rectangle, primitive: impl Primitive) -> bool {
+        match self {
+            Self::Primary(renderer) => renderer.draw_video_primitive(bounds, primitive),
+            Self::Secondary(renderer) => renderer.draw_video_primitive(bounds, primitive),
+        }
+    }
+}
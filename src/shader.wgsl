@@ -5,8 +5,37 @@ struct VertexOutput {
 
 struct Uniforms {
     rect: vec4<f32>,
+    // rotation and mirroring around the center of rect, in normalized device coordinates
+    transform: vec4<f32>,
+    // visible region of the frame: min uv, max uv
+    uv_rect: vec4<f32>,
+    // brightness, contrast, saturation, hue
+    adjustments: vec4<f32>,
+    // LUT input domain, with the blend strength in min.w and the LUT size in max.w
+    lut_domain_min: vec4<f32>,
+    lut_domain_max: vec4<f32>,
+    // visible bounds in pixels of the render target: min, max
+    frame_rect: vec4<f32>,
+    // corner radii: top left, top right, bottom right, bottom left
+    radius: vec4<f32>,
+    border_color: vec4<f32>,
+    // region of the frame covered by bitmap subtitles: min uv, max uv
+    subtitle_rect: vec4<f32>,
+    gamma: f32,
+    // one of the FILTER_* constants
+    filter_mode: u32,
+    border_width: f32,
+    opacity: f32,
 }
 
+const FILTER_NEAREST: u32 = 0u;
+const FILTER_BILINEAR: u32 = 1u;
+const FILTER_BICUBIC: u32 = 2u;
+const FILTER_LANCZOS: u32 = 3u;
+const FILTER_MIPMAPPED: u32 = 4u;
+
+const PI: f32 = 3.14159265;
+
 @group(0) @binding(0)
 var tex_y: texture_2d<f32>;
 
@@ -19,6 +48,20 @@ var s: sampler;
 @group(0) @binding(3)
 var<uniform> uniforms: Uniforms;
 
+// a single opaque texel for video without alpha
+@group(0) @binding(5)
+var tex_a: texture_2d<f32>;
+
+// premultiplied bitmap subtitles, or a single transparent texel
+@group(0) @binding(6)
+var tex_subtitle: texture_2d<f32>;
+
+@group(1) @binding(0)
+var lut: texture_3d<f32>;
+
+@group(1) @binding(1)
+var lut_sampler: sampler;
+
 @vertex
 fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
     var quad = array<vec4<f32>, 6>(
@@ -30,12 +73,102 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
         vec4<f32>(uniforms.rect.xw, 0.0, 1.0),
     );
 
+    let center = (uniforms.rect.xy + uniforms.rect.zw) / 2.0;
+    let transform = mat2x2<f32>(uniforms.transform.xy, uniforms.transform.zw);
+
     var out: VertexOutput;
-    out.uv = quad[in_vertex_index].zw;
-    out.position = vec4<f32>(quad[in_vertex_index].xy, 1.0, 1.0);
+    out.uv = mix(uniforms.uv_rect.xy, uniforms.uv_rect.zw, quad[in_vertex_index].zw);
+    out.position = vec4<f32>(center + transform * (quad[in_vertex_index].xy - center), 1.0, 1.0);
     return out;
 }
 
+// Catmull-Rom spline
+fn bicubic_weight(x: f32) -> f32 {
+    let t = abs(x);
+    if t < 1.0 {
+        return (1.5 * t - 2.5) * t * t + 1.0;
+    }
+    if t < 2.0 {
+        return ((-0.5 * t + 2.5) * t - 4.0) * t + 2.0;
+    }
+    return 0.0;
+}
+
+// Lanczos with 3 lobes
+fn lanczos_weight(x: f32) -> f32 {
+    let t = abs(x);
+    if t < 1e-5 {
+        return 1.0;
+    }
+    if t >= 3.0 {
+        return 0.0;
+    }
+    let px = PI * t;
+    return 3.0 * sin(px) * sin(px / 3.0) / (px * px);
+}
+
+// separable kernel over the (2 * radius)^2 texels around `uv`
+fn sample_kernel(tex: texture_2d<f32>, uv: vec2<f32>, radius: i32) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(tex));
+    let position = uv * vec2<f32>(size) - 0.5;
+    let base = floor(position);
+    let fraction = position - base;
+
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var y = 1 - radius; y <= radius; y++) {
+        for (var x = 1 - radius; x <= radius; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) - fraction;
+            var weight: f32;
+            if radius == 2 {
+                weight = bicubic_weight(offset.x) * bicubic_weight(offset.y);
+            } else {
+                weight = lanczos_weight(offset.x) * lanczos_weight(offset.y);
+            }
+            let texel = clamp(vec2<i32>(base) + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            sum += textureLoad(tex, texel, 0) * weight;
+            total += weight;
+        }
+    }
+    return sum / total;
+}
+
+fn sample_plane(tex: texture_2d<f32>, uv: vec2<f32>) -> vec4<f32> {
+    switch uniforms.filter_mode {
+        case FILTER_NEAREST: {
+            // loaded rather than sampled, as OpenGL can't use a texture with more than one sampler
+            let size = vec2<i32>(textureDimensions(tex));
+            let texel = clamp(vec2<i32>(floor(uv * vec2<f32>(size))), vec2<i32>(0), size - 1);
+            return textureLoad(tex, texel, 0);
+        }
+        case FILTER_BICUBIC: {
+            return sample_kernel(tex, uv, 2);
+        }
+        case FILTER_LANCZOS: {
+            return sample_kernel(tex, uv, 3);
+        }
+        case FILTER_MIPMAPPED: {
+            return textureSample(tex, s, uv);
+        }
+        default: {
+            // mip levels are only kept up to date when mipmapping, so stick to the full resolution level
+            return textureSampleLevel(tex, s, uv, 0.0);
+        }
+    }
+}
+
+// signed distance to the edge of a rounded box, as used by iced for quads
+fn rounded_box_sdf(to_center: vec2<f32>, size: vec2<f32>, radius: f32) -> f32 {
+    return length(max(abs(to_center) - size + vec2<f32>(radius), vec2<f32>(0.0))) - radius;
+}
+
+fn select_border_radius(radii: vec4<f32>, position: vec2<f32>, center: vec2<f32>) -> f32 {
+    let right = position.x > center.x;
+    let top = select(radii.x, radii.y, right);
+    let bottom = select(radii.w, radii.z, right);
+    return select(top, bottom, position.y > center.y);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // BT.709 precomputed coefficents
@@ -45,12 +178,53 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         1, 1.8556, 0,
     );
 
+    let chroma = sample_plane(tex_uv, in.uv);
+
     var yuv = vec3<f32>(0.0);
-    yuv.x = (textureSample(tex_y, s, in.uv).r - 0.0625) / 0.8588;
-    yuv.y = (textureSample(tex_uv, s, in.uv).r - 0.5) / 0.8784;
-    yuv.z = (textureSample(tex_uv, s, in.uv).g - 0.5) / 0.8784;
+    yuv.x = (sample_plane(tex_y, in.uv).r - 0.0625) / 0.8588;
+    yuv.y = (chroma.r - 0.5) / 0.8784;
+    yuv.z = (chroma.g - 0.5) / 0.8784;
+
+    // hue and saturation rotate and scale the chroma
+    let hue = uniforms.adjustments.w;
+    let rotation = mat2x2<f32>(cos(hue), sin(hue), -sin(hue), cos(hue));
+    yuv = vec3<f32>(yuv.x, rotation * yuv.yz * uniforms.adjustments.z);
+
+    var rgb = yuv * yuv2rgb;
+    rgb = (rgb - 0.5) * uniforms.adjustments.y + 0.5 + uniforms.adjustments.x;
+    rgb = clamp(rgb, vec3<f32>(0), vec3<f32>(1));
+    rgb = pow(rgb, vec3<f32>(1.0 / uniforms.gamma));
+
+    // trilinear lookup, mapping the domain onto the centers of the first and last texels
+    let lut_size = uniforms.lut_domain_max.w;
+    let domain = (rgb - uniforms.lut_domain_min.xyz) / (uniforms.lut_domain_max.xyz - uniforms.lut_domain_min.xyz);
+    let coords = (clamp(domain, vec3<f32>(0), vec3<f32>(1)) * (lut_size - 1.0) + 0.5) / lut_size;
+    let graded = textureSample(lut, lut_sampler, coords).rgb;
+    rgb = mix(rgb, graded, uniforms.lut_domain_min.w);
+
+    // bitmap subtitles over the picture, left out of the grading
+    var picture = vec4<f32>(rgb, sample_plane(tex_a, in.uv).r);
+    let subtitle_size = uniforms.subtitle_rect.zw - uniforms.subtitle_rect.xy;
+    if all(subtitle_size > vec2<f32>(0.0)) {
+        let subtitle_uv = (in.uv - uniforms.subtitle_rect.xy) / subtitle_size;
+        if all(subtitle_uv >= vec2<f32>(0.0)) && all(subtitle_uv <= vec2<f32>(1.0)) {
+            let subtitle = textureSampleLevel(tex_subtitle, s, subtitle_uv, 0.0);
+            let alpha = subtitle.a + picture.a * (1.0 - subtitle.a);
+            let premultiplied = subtitle.rgb + picture.rgb * picture.a * (1.0 - subtitle.a);
+            picture = vec4<f32>(premultiplied / max(alpha, 1e-5), alpha);
+        }
+    }
 
-    var rgb = clamp(yuv * yuv2rgb, vec3<f32>(0), vec3<f32>(1));
+    // rounded corners and border, antialiased over one pixel
+    let frame_min = uniforms.frame_rect.xy;
+    let frame_size = uniforms.frame_rect.zw - frame_min;
+    let center = frame_min + frame_size / 2.0;
+    let radius = select_border_radius(uniforms.radius, in.position.xy, center);
+    let dist = rounded_box_sdf(in.position.xy - center, frame_size / 2.0, min(radius, min(frame_size.x, frame_size.y) / 2.0));
+    let border = select(0.0, clamp(dist + uniforms.border_width + 0.5, 0.0, 1.0), uniforms.border_width > 0.0);
+    let color = mix(picture, uniforms.border_color, border);
 
-    return vec4<f32>(rgb, 1.0);
+    // premultiplied alpha
+    let alpha = color.a * clamp(0.5 - dist, 0.0, 1.0) * uniforms.opacity;
+    return vec4<f32>(color.rgb * alpha, alpha);
 }
@@ -0,0 +1,119 @@
+//! Parsing of SubRip (`.srt`) files into [`SubtitleCue`]s.
+//!
+//! The `<b>`, `<i>`, `<u>` and `<font color>` tags of the text are kept, as they are valid markup.
+
+use crate::subtitle::SubtitleCue;
+use std::time::Duration;
+
+/// Parses the cues of a whole file, ordered by start time. Malformed cues are skipped.
+pub(crate) fn parse_file(text: &str) -> Vec<SubtitleCue> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+
+    for block in text.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| line.trim().is_empty());
+        // the counter before the timing line is sometimes missing
+        let Some(timing) = lines.next().and_then(|line| {
+            if line.contains("-->") {
+                Some(line)
+            } else {
+                lines.next()
+            }
+        }) else {
+            continue;
+        };
+        let Some((start, end)) = timing.split_once("-->") else {
+            continue;
+        };
+        // coordinates may follow the end time
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let (Some(start), Some(end)) = (parse_time(start.trim()), parse_time(end)) else {
+            continue;
+        };
+
+        cues.push(SubtitleCue {
+            start,
+            end,
+            markup: lines.collect::<Vec<_>>().join("\n"),
+            ..SubtitleCue::default()
+        });
+    }
+
+    cues.sort_by_key(|cue| cue.start);
+    cues
+}
+
+/// Parses `HH:MM:SS,mmm`, also accepting a `.` before the milliseconds.
+fn parse_time(value: &str) -> Option<Duration> {
+    let (time, millis) = value.split_once([',', '.']).unwrap_or((value, "0"));
+    let mut parts = time.split(':');
+    let hours: u64 = parts.next()?.trim().parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    let seconds = hours
+        .checked_mul(3600)?
+        .checked_add(minutes.checked_mul(60)?)?
+        .checked_add(seconds)?;
+    Duration::from_secs(seconds).checked_add(Duration::from_millis(millis.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(
+            parse_time("01:02:03,456"),
+            Some(Duration::from_millis(3_723_456))
+        );
+        assert_eq!(
+            parse_time("00:00:01.500"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_time("00:00:07"), Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for value in [
+            "",
+            "00:01",
+            "-1:00:00,000",
+            "00:00:-1,000",
+            "00:00:01,-5",
+            "aa:00:00,000",
+            "18446744073709551615:00:00,000",
+        ] {
+            assert_eq!(parse_time(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn parses_file() {
+        let cues = parse_file(
+            "\u{feff}2\r\n00:00:05,000 --> 00:00:06,000\r\nSecond\r\n\r\n\
+             1\n00:00:01,000 --> 00:00:02,500 X1:0 X2:10\n<i>First</i>\nline\n\n\
+             3\nnot a timing\nSkipped\n\n\
+             00:00:03,000 --> 00:00:04,000\nNo counter\n",
+        );
+        let cues: Vec<_> = cues
+            .iter()
+            .map(|cue| {
+                (
+                    cue.start.as_millis(),
+                    cue.end.as_millis(),
+                    cue.markup.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (1000, 2500, "<i>First</i>\nline"),
+                (3000, 4000, "No counter"),
+                (5000, 6000, "Second"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use iced::{Background, Border, Color};
+
+/// The appearance of a [`VideoPlayer`](crate::VideoPlayer).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    /// Opacity of the whole widget, from `0.0` (invisible) to `1.0` (opaque).
+    pub opacity: f32,
+    /// Border drawn on the inside edge of the video. Its radius rounds the corners of the video.
+    pub border: Border,
+    /// Background filling the widget behind the video, visible as letterbox or pillarbox bars.
+    pub background: Option<Background>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            opacity: 1.0,
+            border: Border::default(),
+            background: None,
+        }
+    }
+}
+
+impl Style {
+    /// Updates the opacity of the [`Style`].
+    pub fn with_opacity(self, opacity: f32) -> Self {
+        Style { opacity, ..self }
+    }
+
+    /// Updates the border of the [`Style`].
+    pub fn with_border(self, color: impl Into<Color>, width: f32) -> Self {
+        Style {
+            border: Border {
+                color: color.into(),
+                width,
+                ..self.border
+            },
+            ..self
+        }
+    }
+
+    /// Updates the border radius of the [`Style`].
+    pub fn with_radius(self, radius: impl Into<iced::border::Radius>) -> Self {
+        Style {
+            border: self.border.rounded(radius),
+            ..self
+        }
+    }
+
+    /// Updates the background of the [`Style`].
+    pub fn with_background(self, background: impl Into<Background>) -> Self {
+        Style {
+            background: Some(background.into()),
+            ..self
+        }
+    }
+}
+
+/// The theme catalog of a [`VideoPlayer`](crate::VideoPlayer).
+pub trait Catalog {
+    /// The item class of the [`Catalog`].
+    type Class<'a>;
+
+    /// The default class produced by the [`Catalog`].
+    fn default<'a>() -> Self::Class<'a>;
+
+    /// The [`Style`] of a class.
+    fn style(&self, class: &Self::Class<'_>) -> Style;
+}
+
+/// A styling function for a [`VideoPlayer`](crate::VideoPlayer).
+pub type StyleFn<'a, Theme> = Box<dyn Fn(&Theme) -> Style + 'a>;
+
+impl<Theme> From<Style> for StyleFn<'_, Theme> {
+    fn from(style: Style) -> Self {
+        Box::new(move |_theme| style)
+    }
+}
+
+impl Catalog for iced::Theme {
+    type Class<'a> = StyleFn<'a, Self>;
+
+    fn default<'a>() -> Self::Class<'a> {
+        Box::new(|_theme| Style::default())
+    }
+
+    fn style(&self, class: &Self::Class<'_>) -> Style {
+        class(self)
+    }
+}
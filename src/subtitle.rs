@@ -0,0 +1,729 @@
+use iced::advanced::text::{self, Paragraph, Span};
+use iced::alignment::{Horizontal, Vertical};
+use iced::{Color, Font, Pixels, Point, Rectangle, Size, font};
+use std::time::Duration;
+
+/// The appearance of subtitles drawn by a [`VideoPlayer`](crate::VideoPlayer).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubtitleStyle {
+    /// Font of the text. Bold and italic markup select the matching variant of it.
+    pub font: Font,
+    /// Size of the text.
+    pub size: Pixels,
+    /// Color of text which isn't colored by markup.
+    pub color: Color,
+    /// Color of the outline drawn around the text.
+    pub outline_color: Color,
+    /// Width of the outline in logical pixels, or `0.0` for no outline.
+    pub outline_width: f32,
+    /// Distance between the bottom of the text and the bottom of the video in logical pixels.
+    pub bottom_margin: f32,
+}
+
+impl Default for SubtitleStyle {
+    fn default() -> Self {
+        SubtitleStyle {
+            font: Font::DEFAULT,
+            size: Pixels(24.0),
+            color: Color::WHITE,
+            outline_color: Color::BLACK,
+            outline_width: 2.0,
+            bottom_margin: 24.0,
+        }
+    }
+}
+
+impl SubtitleStyle {
+    /// Updates the font of the [`SubtitleStyle`].
+    pub fn with_font(self, font: impl Into<Font>) -> Self {
+        SubtitleStyle {
+            font: font.into(),
+            ..self
+        }
+    }
+
+    /// Updates the text size of the [`SubtitleStyle`].
+    pub fn with_size(self, size: impl Into<Pixels>) -> Self {
+        SubtitleStyle {
+            size: size.into(),
+            ..self
+        }
+    }
+
+    /// Updates the text color of the [`SubtitleStyle`].
+    pub fn with_color(self, color: impl Into<Color>) -> Self {
+        SubtitleStyle {
+            color: color.into(),
+            ..self
+        }
+    }
+
+    /// Updates the outline of the [`SubtitleStyle`].
+    pub fn with_outline(self, color: impl Into<Color>, width: f32) -> Self {
+        SubtitleStyle {
+            outline_color: color.into(),
+            outline_width: width,
+            ..self
+        }
+    }
+
+    /// Updates the bottom margin of the [`SubtitleStyle`].
+    pub fn with_bottom_margin(self, bottom_margin: f32) -> Self {
+        SubtitleStyle {
+            bottom_margin,
+            ..self
+        }
+    }
+}
+
+/// A subtitle cue: styled text shown over a span of the stream, optionally placed and styled by
+/// the subtitle format (e.g., ASS/SSA or WebVTT) instead of by the [`SubtitleStyle`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleCue {
+    /// Stream time at which the cue appears.
+    pub start: Duration,
+    /// Stream time at which the cue disappears.
+    pub end: Duration,
+    /// Text of the cue as Pango markup.
+    pub markup: String,
+    /// Horizontal anchor as a fraction of the video width, or `None` to center the cue.
+    pub x: Option<f32>,
+    /// Vertical anchor as a fraction of the video height, or `None` to place the cue at the bottom.
+    pub y: Option<f32>,
+    /// Horizontal alignment of the cue relative to its anchor.
+    pub align_x: Horizontal,
+    /// Vertical alignment of the cue relative to its anchor.
+    pub align_y: Vertical,
+    /// Color of uncolored text, overriding [`SubtitleStyle::color`].
+    pub color: Option<Color>,
+    /// Color of the outline, overriding [`SubtitleStyle::outline_color`].
+    pub outline_color: Option<Color>,
+    /// Text size as a fraction of the video height, overriding [`SubtitleStyle::size`].
+    pub size: Option<f32>,
+}
+
+impl Default for SubtitleCue {
+    fn default() -> Self {
+        SubtitleCue {
+            start: Duration::ZERO,
+            end: Duration::ZERO,
+            markup: String::new(),
+            x: None,
+            y: None,
+            align_x: Horizontal::Center,
+            align_y: Vertical::Bottom,
+            color: None,
+            outline_color: None,
+            size: None,
+        }
+    }
+}
+
+impl SubtitleCue {
+    /// Returns the text of the cue without markup.
+    pub fn text(&self) -> String {
+        parse_markup(&self.markup, Font::DEFAULT)
+            .into_iter()
+            .map(|span| span.text)
+            .collect()
+    }
+}
+
+/// The format of subtitles loaded with [`Video::load_subtitles_from_str`](crate::Video::load_subtitles_from_str).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubtitleFormat {
+    /// SubRip (`.srt`).
+    Srt,
+    /// WebVTT (`.vtt`).
+    Vtt,
+    /// Advanced SubStation Alpha or SubStation Alpha (`.ass` or `.ssa`).
+    Ass,
+}
+
+/// Image-based subtitles (e.g., PGS, VobSub or DVB), composited over the video.
+#[derive(Debug, Clone)]
+pub(crate) struct Bitmap {
+    /// Premultiplied RGBA pixels, row by row.
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Region of the frame covered by the bitmap in normalized texture coordinates.
+    pub rect: Rectangle,
+}
+
+impl Bitmap {
+    /// Blends the bitmap over a frame of straight (not premultiplied) RGBA pixels.
+    pub fn composite(&self, frame: &mut [u8], width: u32, height: u32) {
+        let x0 = (self.rect.x * width as f32).round().max(0.0) as u32;
+        let y0 = (self.rect.y * height as f32).round().max(0.0) as u32;
+        let x1 = (((self.rect.x + self.rect.width) * width as f32).round() as u32).min(width);
+        let y1 = (((self.rect.y + self.rect.height) * height as f32).round() as u32).min(height);
+        if x1 <= x0 || y1 <= y0 || self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        for y in y0..y1 {
+            let source_y = ((y - y0) * self.height / (y1 - y0)).min(self.height - 1);
+            for x in x0..x1 {
+                let source_x = ((x - x0) * self.width / (x1 - x0)).min(self.width - 1);
+                let source = ((source_y * self.width + source_x) * 4) as usize;
+                let [r, g, b, a] = [0, 1, 2, 3].map(|i| self.pixels[source + i] as f32 / 255.0);
+                if a == 0.0 {
+                    continue;
+                }
+
+                let target = ((y * width + x) * 4) as usize;
+                let pixel = &mut frame[target..target + 4];
+                let alpha = pixel[3] as f32 / 255.0;
+                let out_alpha = a + alpha * (1.0 - a);
+                for (channel, source) in pixel[..3].iter_mut().zip([r, g, b]) {
+                    let premultiplied = source + *channel as f32 / 255.0 * alpha * (1.0 - a);
+                    *channel = (premultiplied / out_alpha * 255.0).round().min(255.0) as u8;
+                }
+                pixel[3] = (out_alpha * 255.0).round() as u8;
+            }
+        }
+    }
+}
+
+/// A cue laid out for drawing, along with an uncolored copy for its outline.
+#[derive(Debug)]
+struct Layout<P> {
+    text: P,
+    outline: P,
+    color: Color,
+    outline_color: Color,
+}
+
+/// Subtitle cues laid out for drawing.
+#[derive(Debug, Default)]
+pub(crate) struct Overlay<P> {
+    cues: Vec<SubtitleCue>,
+    style: Option<SubtitleStyle>,
+    frame: Size,
+    layouts: Vec<Layout<P>>,
+}
+
+impl<P: Paragraph<Font = Font>> Overlay<P> {
+    /// Lays out `cues` with `style` for a video of size `frame`.
+    /// Does nothing if none of them changed since the last call.
+    pub fn update(&mut self, cues: &[SubtitleCue], style: &SubtitleStyle, frame: Size) {
+        if self.cues == cues && self.style.as_ref() == Some(style) && self.frame == frame {
+            return;
+        }
+
+        // positioned cues may span the whole width, others are kept off the edges
+        let max_width = |cue: &SubtitleCue| {
+            if cue.x.is_some() {
+                frame.width
+            } else {
+                frame.width * 0.9
+            }
+        };
+
+        self.layouts = cues
+            .iter()
+            .map(|cue| {
+                let spans = parse_markup(&cue.markup, style.font);
+                let text = text::Text {
+                    content: spans.as_slice(),
+                    bounds: Size::new(max_width(cue).max(0.0), f32::INFINITY),
+                    size: cue
+                        .size
+                        .map_or(style.size, |size| Pixels(size * frame.height)),
+                    line_height: text::LineHeight::default(),
+                    font: style.font,
+                    align_x: match cue.align_x {
+                        Horizontal::Left => text::Alignment::Left,
+                        Horizontal::Center => text::Alignment::Center,
+                        Horizontal::Right => text::Alignment::Right,
+                    },
+                    align_y: Vertical::Top,
+                    shaping: text::Shaping::Advanced,
+                    wrapping: text::Wrapping::WordOrGlyph,
+                };
+
+                // the outline is drawn in a single color, so drop any given by markup
+                let uncolored: Vec<_> = spans
+                    .iter()
+                    .cloned()
+                    .map(|span| Span {
+                        color: None,
+                        ..span
+                    })
+                    .collect();
+
+                Layout {
+                    text: P::with_spans(text),
+                    outline: P::with_spans(text.with_content(uncolored.as_slice())),
+                    color: cue.color.unwrap_or(style.color),
+                    outline_color: cue.outline_color.unwrap_or(style.outline_color),
+                }
+            })
+            .collect();
+
+        self.cues = cues.to_vec();
+        self.style = Some(*style);
+        self.frame = frame;
+    }
+
+    /// Draws the cues over `video_bounds`, stacking unpositioned ones upwards from the bottom.
+    pub fn draw<Renderer>(&self, renderer: &mut Renderer, video_bounds: Rectangle)
+    where
+        Renderer: text::Renderer<Font = Font, Paragraph = P>,
+    {
+        let Some(style) = self.style else {
+            return;
+        };
+
+        let mut bottom = video_bounds.y + video_bounds.height - style.bottom_margin;
+        for (cue, layout) in self.cues.iter().zip(&self.layouts) {
+            if cue.markup.trim().is_empty() {
+                continue;
+            }
+
+            let size = layout.text.min_bounds();
+            let position = match (cue.x, cue.y) {
+                (None, None) => {
+                    bottom -= size.height;
+                    Point::new(video_bounds.center_x() - size.width / 2.0, bottom)
+                }
+                (x, y) => {
+                    let anchor = Point::new(
+                        video_bounds.x + x.unwrap_or(0.5) * video_bounds.width,
+                        video_bounds.y + y.unwrap_or(1.0) * video_bounds.height,
+                    );
+                    Point::new(
+                        match cue.align_x {
+                            Horizontal::Left => anchor.x,
+                            Horizontal::Center => anchor.x - size.width / 2.0,
+                            Horizontal::Right => anchor.x - size.width,
+                        },
+                        match cue.align_y {
+                            Vertical::Top => anchor.y,
+                            Vertical::Center => anchor.y - size.height / 2.0,
+                            Vertical::Bottom => anchor.y - size.height,
+                        },
+                    )
+                }
+            };
+
+            if style.outline_width > 0.0 {
+                let width = style.outline_width;
+                for (x, y) in [
+                    (-1.0, -1.0),
+                    (0.0, -1.0),
+                    (1.0, -1.0),
+                    (-1.0, 0.0),
+                    (1.0, 0.0),
+                    (-1.0, 1.0),
+                    (0.0, 1.0),
+                    (1.0, 1.0),
+                ] {
+                    renderer.fill_paragraph(
+                        &layout.outline,
+                        position + iced::Vector::new(x * width, y * width),
+                        layout.outline_color,
+                        video_bounds,
+                    );
+                }
+            }
+
+            renderer.fill_paragraph(&layout.text, position, layout.color, video_bounds);
+        }
+    }
+}
+
+/// Text formatting in effect at some point of the markup.
+#[derive(Debug, Clone, Copy)]
+struct Format {
+    font: Font,
+    color: Option<Color>,
+    underline: bool,
+    strikethrough: bool,
+}
+
+/// Parses the Pango markup delivered by GStreamer's subtitle parsers (e.g., `<b>`, `<i>`, `<u>`, `<s>`,
+/// and `<span>` or SRT's `<font>` with colors) into spans based on `font`.
+///
+/// Unknown tags are dropped; a stray `<` is kept as text.
+pub(crate) fn parse_markup(markup: &str, font: Font) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut stack = vec![Format {
+        font,
+        color: None,
+        underline: false,
+        strikethrough: false,
+    }];
+    let mut text = String::new();
+
+    let flush = |text: &mut String, spans: &mut Vec<Span<'static>>, format: &Format| {
+        if !text.is_empty() {
+            spans.push(Span {
+                font: Some(format.font),
+                color: format.color,
+                underline: format.underline,
+                strikethrough: format.strikethrough,
+                ..Span::new(std::mem::take(text))
+            });
+        }
+    };
+
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        decode_entities(&rest[..start], &mut text);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim();
+        rest = &rest[start + end + 1..];
+
+        let format = *stack.last().unwrap();
+        flush(&mut text, &mut spans, &format);
+
+        if tag.starts_with('/') {
+            if stack.len() > 1 {
+                stack.pop();
+            }
+            continue;
+        }
+
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let mut format = format;
+        match name.to_ascii_lowercase().as_str() {
+            "b" => format.font.weight = font::Weight::Bold,
+            "i" => format.font.style = font::Style::Italic,
+            "u" => format.underline = true,
+            "s" => format.strikethrough = true,
+            "span" | "font" => {
+                for (key, value) in parse_attributes(attributes) {
+                    match key {
+                        "foreground" | "fgcolor" | "color" => {
+                            format.color = parse_color(value).or(format.color);
+                        }
+                        "weight" => {
+                            format.font.weight = match value {
+                                "bold" | "ultrabold" | "heavy" => font::Weight::Bold,
+                                "semibold" => font::Weight::Semibold,
+                                "light" | "ultralight" => font::Weight::Light,
+                                "normal" => font::Weight::Normal,
+                                _ => match value.parse::<u16>() {
+                                    Ok(600..) => font::Weight::Bold,
+                                    _ => format.font.weight,
+                                },
+                            }
+                        }
+                        "style" => {
+                            format.font.style = match value {
+                                "italic" => font::Style::Italic,
+                                "oblique" => font::Style::Oblique,
+                                _ => font::Style::Normal,
+                            }
+                        }
+                        "underline" => format.underline = value != "none",
+                        "strikethrough" => format.strikethrough = value == "true",
+                        _ => {}
+                    }
+                }
+            }
+            // line breaks are void elements, with or without the closing slash
+            "br" | "br/" => {
+                text.push('\n');
+                continue;
+            }
+            // self-closing tags don't affect the following text
+            _ if tag.ends_with('/') => continue,
+            _ => {}
+        }
+        stack.push(format);
+    }
+
+    decode_entities(rest, &mut text);
+    flush(&mut text, &mut spans, stack.last().unwrap());
+
+    spans
+}
+
+/// Iterates over the `key="value"` pairs of a tag.
+fn parse_attributes(attributes: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = attributes;
+    std::iter::from_fn(move || {
+        let (key, value) = rest.split_once('=')?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let (value, remainder) = match quote {
+            Some(quote) => value[1..].split_once(quote).unwrap_or((&value[1..], "")),
+            None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+        };
+        rest = remainder;
+        Some((key.trim(), value))
+    })
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    Some(match value.to_ascii_lowercase().as_str() {
+        "white" => Color::WHITE,
+        "black" => Color::BLACK,
+        "red" => Color::from_rgb8(0xFF, 0x00, 0x00),
+        "green" => Color::from_rgb8(0x00, 0x80, 0x00),
+        "lime" => Color::from_rgb8(0x00, 0xFF, 0x00),
+        "blue" => Color::from_rgb8(0x00, 0x00, 0xFF),
+        "yellow" => Color::from_rgb8(0xFF, 0xFF, 0x00),
+        "cyan" | "aqua" => Color::from_rgb8(0x00, 0xFF, 0xFF),
+        "magenta" | "fuchsia" => Color::from_rgb8(0xFF, 0x00, 0xFF),
+        "gray" | "grey" => Color::from_rgb8(0x80, 0x80, 0x80),
+        // `Color::from_str` slices by bytes, so only ASCII is safe to pass on
+        _ => value
+            .strip_prefix('#')
+            .filter(|hex| hex.is_ascii())?
+            .parse()
+            .ok()?,
+    })
+}
+
+/// Escapes the characters of `text` which are special in markup.
+pub(crate) fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends `text` to `out`, replacing XML character entities.
+fn decode_entities(text: &str, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+
+        match decoded {
+            Some((c, length)) => {
+                out.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of each span, and whether it's bold and italic.
+    fn styles(markup: &str) -> Vec<(String, bool, bool)> {
+        parse_markup(markup, Font::DEFAULT)
+            .into_iter()
+            .map(|span| {
+                let font = span.font.unwrap();
+                (
+                    span.text.into_owned(),
+                    font.weight == font::Weight::Bold,
+                    font.style == font::Style::Italic,
+                )
+            })
+            .collect()
+    }
+
+    fn text(markup: &str) -> String {
+        SubtitleCue {
+            markup: markup.to_string(),
+            ..SubtitleCue::default()
+        }
+        .text()
+    }
+
+    #[test]
+    fn parses_nested_tags() {
+        assert_eq!(
+            styles("a<b>b<i>c</i>d</b>e"),
+            [
+                ("a".to_string(), false, false),
+                ("b".to_string(), true, false),
+                ("c".to_string(), true, true),
+                ("d".to_string(), true, false),
+                ("e".to_string(), false, false),
+            ]
+        );
+        // unbalanced closing tags don't pop the base format
+        assert_eq!(
+            styles("</b></i>a<I>b"),
+            [
+                ("a".to_string(), false, false),
+                ("b".to_string(), false, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_spans() {
+        let spans = parse_markup(
+            "<span foreground=\"#ff0000\" weight='bold' underline=single>a</span><font color=lime>b</font><u><s>c</s></u>",
+            Font::DEFAULT,
+        );
+        assert_eq!(spans[0].color, Some(Color::from_rgb8(0xFF, 0, 0)));
+        assert_eq!(spans[0].font.unwrap().weight, font::Weight::Bold);
+        assert!(spans[0].underline);
+        assert_eq!(spans[1].color, Some(Color::from_rgb8(0, 0xFF, 0)));
+        assert!(spans[2].underline && spans[2].strikethrough);
+
+        // unknown and non-ASCII colors are ignored
+        let spans = parse_markup(
+            "<font color=\"#ééé\">a<font color=nope>b</font></font>",
+            Font::DEFAULT,
+        );
+        assert!(spans.iter().all(|span| span.color.is_none()));
+    }
+
+    #[test]
+    fn keeps_stray_brackets() {
+        assert_eq!(text("1 < 2"), "1 < 2");
+        assert_eq!(text("2 > 1"), "2 > 1");
+        assert_eq!(text("<b>a</b> <"), "a <");
+        // unknown tags are dropped
+        assert_eq!(text("<ruby>a</ruby>"), "a");
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            text("&lt;b&gt; &amp; &quot;&apos; &#65;&#x42;&nbsp;"),
+            "<b> & \"' AB\u{a0}"
+        );
+        // unknown or unterminated entities are kept as is
+        assert_eq!(text("&bogus; & &#xZZ;"), "&bogus; & &#xZZ;");
+        assert_eq!(text(&escape_markup("<i>&</i>")), "<i>&</i>");
+    }
+
+    #[test]
+    fn breaks_lines() {
+        assert_eq!(text("a<br>b<br/>c<br />d"), "a\nb\nc\nd");
+        // line breaks don't need closing, so they don't affect the following format
+        assert_eq!(
+            styles("<i>a<br>b</i>c"),
+            [
+                ("a".to_string(), false, true),
+                ("\nb".to_string(), false, true),
+                ("c".to_string(), false, false)
+            ]
+        );
+    }
+
+    fn bitmap(pixels: &[[u8; 4]], width: u32, rect: Rectangle) -> Bitmap {
+        Bitmap {
+            pixels: pixels.concat(),
+            width,
+            height: pixels.len() as u32 / width,
+            rect,
+        }
+    }
+
+    #[test]
+    fn composites_bitmaps() {
+        // 4x2 frame of opaque gray
+        let gray = [100, 100, 100, 255];
+        let mut frame = [gray; 8].concat();
+        // opaque red, transparent, and half transparent premultiplied white, scaled over the right half
+        let overlay = bitmap(
+            &[
+                [255, 0, 0, 255],
+                [0, 0, 0, 0],
+                [128, 128, 128, 128],
+                [255, 0, 0, 255],
+            ],
+            2,
+            Rectangle::new(Point::new(0.5, 0.0), Size::new(0.5, 1.0)),
+        );
+        overlay.composite(&mut frame, 4, 2);
+
+        let pixels: Vec<_> = frame.chunks_exact(4).collect();
+        assert_eq!(pixels[0], gray);
+        assert_eq!(pixels[1], gray);
+        assert_eq!(pixels[2], [255, 0, 0, 255]);
+        assert_eq!(pixels[3], gray);
+        assert_eq!(pixels[6], [178, 178, 178, 255]);
+        assert_eq!(pixels[7], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn composites_over_transparency() {
+        // half transparent premultiplied red over a transparent frame keeps its straight color
+        let mut frame = vec![0; 4];
+        let overlay = bitmap(
+            &[[128, 0, 0, 128]],
+            1,
+            Rectangle::new(Point::ORIGIN, Size::new(1.0, 1.0)),
+        );
+        overlay.composite(&mut frame, 1, 1);
+        assert_eq!(frame, [255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn clips_bitmaps() {
+        let mut frame = vec![0; 2 * 2 * 4];
+        // extends past the bottom right corner
+        let overlay = bitmap(
+            &[[255; 4]],
+            1,
+            Rectangle::new(Point::new(0.5, 0.5), Size::new(1.0, 1.0)),
+        );
+        overlay.composite(&mut frame, 2, 2);
+        assert_eq!(frame[..12], [0; 12]);
+        assert_eq!(frame[12..], [255; 4]);
+
+        // empty rectangles and bitmaps are skipped
+        let mut frame = vec![0; 4];
+        let empty = bitmap(&[[255; 4]], 1, Rectangle::new(Point::ORIGIN, Size::ZERO));
+        empty.composite(&mut frame, 1, 1);
+        let empty = bitmap(&[], 1, Rectangle::new(Point::ORIGIN, Size::new(1.0, 1.0)));
+        empty.composite(&mut frame, 1, 1);
+        assert_eq!(frame, [0; 4]);
+    }
+
+    #[test]
+    fn parses_attributes() {
+        let attributes: Vec<_> =
+            parse_attributes(" a=\"1 2\"  b='3' c=4 d = \"5\" e=\"unterminated").collect();
+        assert_eq!(
+            attributes,
+            [
+                ("a", "1 2"),
+                ("b", "3"),
+                ("c", "4"),
+                ("d", "5"),
+                ("e", "unterminated")
+            ]
+        );
+        assert_eq!(parse_attributes("novalue").count(), 0);
+    }
+}
@@ -0,0 +1,334 @@
+//! Headless rendering of a [`VideoPlayer`], for verifying its output without a display.
+//!
+//! Requires the `testing` feature.
+//!
+//! ```rust,no_run
+//! use iced_video_player::{VideoPlayer, testing};
+//!
+//! let video = testing::test_video("smpte", 320, 240).unwrap();
+//! let mut headless = testing::Headless::new().unwrap();
+//!
+//! let player: VideoPlayer<()> = VideoPlayer::new(&video).content_fit(iced::ContentFit::Contain);
+//! let snapshot = headless
+//!     .render(&player, &iced::Theme::Dark, iced::Size::new(640, 240))
+//!     .unwrap();
+//!
+//! // pillarboxed, so the left edge is untouched
+//! assert_eq!(snapshot.pixel(0, 120), [0, 0, 0, 0]);
+//! ```
+
+use crate::renderer::Renderer as VideoRenderer;
+use crate::video_player::State;
+use crate::{Catalog, Error, Video, VideoPlayer};
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+use gstreamer_app::prelude::*;
+use iced_wgpu::graphics::Viewport;
+use iced_wgpu::primitive::{Pipeline, Primitive};
+use iced_wgpu::wgpu;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Creates a video from a GStreamer `videotestsrc` with the given `pattern` (e.g., `"smpte"`, `"ball"`, `"red"`).
+///
+/// The video has no audio and plays at 30 frames per second.
+pub fn test_video(pattern: &str, width: u32, height: u32) -> Result<Video, Error> {
+    gst::init()?;
+
+    let pipeline = format!(
+        "videotestsrc pattern={pattern} ! video/x-raw,width={width},height={height},framerate=30/1 ! videoconvert ! appsink name=iced_video drop=true caps=video/x-raw,format=NV12"
+    );
+    let pipeline = gst::parse::launch(pipeline.as_ref())?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| Error::Cast)?;
+
+    let video_sink = pipeline
+        .by_name("iced_video")
+        .ok_or_else(|| Error::AppSink("iced_video".to_string()))?
+        .downcast::<gst_app::AppSink>()
+        .map_err(|_| Error::Cast)?;
+
+    Video::from_gst_pipeline(pipeline, video_sink, None)
+}
+
+/// The pixels of a rendered frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Tightly packed RGBA8 pixels, row by row.
+    pub rgba: Vec<u8>,
+}
+
+impl Snapshot {
+    /// Get the RGBA value of the pixel at `(x, y)`.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        [
+            self.rgba[i],
+            self.rgba[i + 1],
+            self.rgba[i + 2],
+            self.rgba[i + 3],
+        ]
+    }
+}
+
+/// Failure to set up the wgpu device of a [`Headless`] renderer.
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceError {
+    #[error("failed to get a wgpu adapter: {0}")]
+    Adapter(#[from] wgpu::RequestAdapterError),
+    #[error("failed to get a wgpu device: {0}")]
+    Device(#[from] wgpu::RequestDeviceError),
+}
+
+/// Renders [`VideoPlayer`]s into an offscreen texture.
+///
+/// Prefers a software adapter (e.g., llvmpipe, WARP) so results don't depend on the GPU of the machine.
+pub struct Headless {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: crate::pipeline::VideoPipeline,
+}
+
+impl Headless {
+    /// Creates the wgpu device used for rendering.
+    pub fn new() -> Result<Self, DeviceError> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            force_fallback_adapter: true,
+            ..Default::default()
+        }))
+        .or_else(|_| {
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        })?;
+
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+                label: Some("iced_video_player headless device"),
+                ..Default::default()
+            }))?;
+
+        let pipeline = crate::pipeline::VideoPipeline::new(&device, &queue, FORMAT);
+
+        Ok(Headless {
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    /// Lays out `player` to fill a target of `size` pixels, waits for its video to produce a frame,
+    /// and renders it with `theme` onto a transparent background.
+    pub fn render<Message, Theme, Renderer>(
+        &mut self,
+        player: &VideoPlayer<'_, Message, Theme, Renderer>,
+        theme: &Theme,
+        size: iced::Size<u32>,
+    ) -> Result<Snapshot, Error>
+    where
+        Theme: Catalog,
+        Renderer: VideoRenderer,
+    {
+        let video = player.video();
+
+        // the worker thread sets this whenever a new sample arrives
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !video.read().upload_frame.swap(false, Ordering::SeqCst) {
+            if Instant::now() > deadline {
+                return Err(Error::Sync);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let bounds =
+            iced::Rectangle::with_size(iced::Size::new(size.width as f32, size.height as f32));
+        let (drawing_bounds, primitive) = {
+            let inner = video.read();
+            let drawing_bounds = player.drawing_bounds(&inner, bounds);
+            let style = theme.style(player.style_class());
+            let frame = drawing_bounds.intersection(&bounds).unwrap_or(bounds);
+            (
+                drawing_bounds,
+                player.primitive(
+                    &inner,
+                    &State::<()>::default(),
+                    &style,
+                    drawing_bounds,
+                    frame,
+                    true,
+                    true,
+                ),
+            )
+        };
+
+        let target = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("iced_video_player headless target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("iced_video_player headless encoder"),
+            });
+
+        // clear to transparent, so letterboxing can be checked
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("iced_video_player headless clear"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        let viewport = Viewport::with_physical_size(size, 1.0);
+        primitive.prepare(
+            &mut self.pipeline,
+            &self.device,
+            &self.queue,
+            &drawing_bounds,
+            &viewport,
+        );
+        let clip = iced::Rectangle::with_size(size);
+        primitive.render(&self.pipeline, &mut encoder, &view, &clip);
+
+        // rows in the readback buffer must be aligned
+        let unpadded_row = size.width * 4;
+        let padded_row = unpadded_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("iced_video_player headless readback"),
+            size: (padded_row * size.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(size.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        readback.map_async(wgpu::MapMode::Read, .., |_| {});
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|_| Error::Sync)?;
+
+        let rgba = readback
+            .get_mapped_range(..)
+            .chunks_exact(padded_row as usize)
+            .flat_map(|row| &row[..unpadded_row as usize])
+            .copied()
+            .collect();
+        readback.unmap();
+
+        Ok(Snapshot {
+            width: size.width,
+            height: size.height,
+            rgba,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders a solid `videotestsrc` pattern of 64x48 pixels into a target of `size`, or `None`
+    /// on machines without any wgpu adapter.
+    fn render(pattern: &str, fit: iced::ContentFit, size: iced::Size<u32>) -> Option<Snapshot> {
+        let mut headless = match Headless::new() {
+            Ok(headless) => headless,
+            Err(err) => {
+                eprintln!("skipping headless rendering: {err}");
+                return None;
+            }
+        };
+        let video = test_video(pattern, 64, 48).unwrap();
+        let player: VideoPlayer<()> = VideoPlayer::new(&video).content_fit(fit);
+        Some(headless.render(&player, &iced::Theme::Dark, size).unwrap())
+    }
+
+    fn assert_color(actual: [u8; 4], expected: [u8; 4]) {
+        // NV12 is limited range and subsampled, so allow for rounding
+        assert!(
+            actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 8),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn converts_colors() {
+        for (pattern, color) in [
+            ("red", [255, 0, 0, 255]),
+            ("green", [0, 255, 0, 255]),
+            ("blue", [0, 0, 255, 255]),
+            ("white", [255, 255, 255, 255]),
+            ("black", [0, 0, 0, 255]),
+        ] {
+            let size = iced::Size::new(64, 48);
+            let Some(snapshot) = render(pattern, iced::ContentFit::Fill, size) else {
+                return;
+            };
+            assert_color(snapshot.pixel(32, 24), color);
+        }
+    }
+
+    #[test]
+    fn fits_content() {
+        let size = iced::Size::new(128, 48);
+        let red = [255, 0, 0, 255];
+
+        // pillarboxed to 64 pixels in the middle
+        let Some(contain) = render("red", iced::ContentFit::Contain, size) else {
+            return;
+        };
+        assert_eq!(contain.pixel(0, 24), [0, 0, 0, 0]);
+        assert_eq!(contain.pixel(127, 24), [0, 0, 0, 0]);
+        assert_color(contain.pixel(33, 24), red);
+        assert_color(contain.pixel(64, 24), red);
+        assert_color(contain.pixel(94, 24), red);
+
+        let Some(fill) = render("red", iced::ContentFit::Fill, size) else {
+            return;
+        };
+        assert_color(fill.pixel(0, 24), red);
+        assert_color(fill.pixel(127, 24), red);
+    }
+}
@@ -1,12 +1,15 @@
 use crate::Error;
+use crate::subtitle::{Bitmap, SubtitleCue, SubtitleFormat, escape_markup};
+use crate::{ass, cea608, srt, waveform, webvtt, yuv};
 use gstreamer as gst;
 use gstreamer_app as gst_app;
 use gstreamer_app::prelude::*;
-use gstreamer_video::VideoMeta;
+use gstreamer_video as gst_video;
+use gstreamer_video::{VideoCaptionMeta, VideoMeta, VideoOverlayCompositionMeta};
 use iced::widget::image as img;
 use std::num::NonZeroU8;
 use std::ops::{Deref, DerefMut};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
@@ -30,6 +33,178 @@ impl From<Position> for gst::GenericFormattedValue {
     }
 }
 
+/// Shift of a stream (e.g., subtitles or audio) relative to the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Delay {
+    /// The stream is presented this long after its timestamps.
+    Later(Duration),
+    /// The stream is presented this long before its timestamps.
+    Earlier(Duration),
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Delay::Later(Duration::ZERO)
+    }
+}
+
+impl Delay {
+    /// Signed nanoseconds, positive when later, saturating at the range of `i64`.
+    fn as_nanos(self) -> i64 {
+        match self {
+            Delay::Later(delay) => i64::try_from(delay.as_nanos()).unwrap_or(i64::MAX),
+            Delay::Earlier(delay) => i64::try_from(delay.as_nanos()).map_or(i64::MIN, |n| -n),
+        }
+    }
+
+    fn from_nanos(nanos: i64) -> Self {
+        let delay = Duration::from_nanos(nanos.unsigned_abs());
+        if nanos < 0 {
+            Delay::Earlier(delay)
+        } else {
+            Delay::Later(delay)
+        }
+    }
+}
+
+/// How interlaced video is deinterlaced before being displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DeinterlaceMode {
+    /// Frames are displayed as decoded; interlaced content will show combing.
+    Off,
+    /// Deinterlaces only content which is flagged as interlaced.
+    #[default]
+    Auto,
+    /// Deinterlaces all content, for interlaced streams which are incorrectly flagged as progressive.
+    Forced,
+}
+
+impl DeinterlaceMode {
+    /// The value of the `mode` property of the GStreamer `deinterlace` element.
+    fn as_gst_mode(self) -> &'static str {
+        match self {
+            DeinterlaceMode::Off => "disabled",
+            DeinterlaceMode::Auto => "auto",
+            DeinterlaceMode::Forced => "interlaced",
+        }
+    }
+}
+
+/// Loudness of the audio, measured by [`Video::set_audio_metering`] and [`Video::set_audio_spectrum`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioLevels {
+    /// Root mean square level of each channel over the last interval, in dB (`0.0` is full scale).
+    pub rms: Vec<f64>,
+    /// Peak level of each channel over the last interval, in dB.
+    pub peak: Vec<f64>,
+    /// Magnitude of each frequency band, from low to high frequencies, in dB down to `-60.0`.
+    pub spectrum: Vec<f32>,
+}
+
+/// Where the audio of a [`Video`] is played.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum AudioOutput {
+    /// The default output of the system, as picked by `playbin`.
+    #[default]
+    Default,
+    /// No audio device is opened, e.g., for silent previews. The audio is still decoded, so
+    /// playback keeps its pace.
+    None,
+    /// An output device, as listed by [`AudioOutput::devices`].
+    Device {
+        /// Identifies the device, unlike its name, which several devices may share.
+        id: String,
+        /// Name of the device to show to users, e.g., `"Built-in Audio Analog Stereo"`.
+        name: String,
+    },
+}
+
+impl AudioOutput {
+    /// Lists the audio output devices of the system (e.g., speakers and headphones), as found by
+    /// a GStreamer device monitor.
+    pub fn devices() -> Result<Vec<AudioOutput>, Error> {
+        Ok(audio_devices()?
+            .iter()
+            .map(|device| AudioOutput::Device {
+                id: device_id(device),
+                name: device.display_name().to_string(),
+            })
+            .collect())
+    }
+
+    /// Creates the element used as `playbin`'s `audio-sink`, or `None` for its default sink.
+    fn sink(&self) -> Result<Option<gst::Element>, Error> {
+        match self {
+            AudioOutput::Default => Ok(None),
+            AudioOutput::None => Ok(Some(
+                gst::ElementFactory::make("fakesink")
+                    .property("sync", true)
+                    .build()?,
+            )),
+            AudioOutput::Device { id, .. } => {
+                let device = audio_devices()?
+                    .into_iter()
+                    .find(|device| device_id(device) == *id)
+                    .ok_or_else(|| Error::AudioDevice(id.clone()))?;
+                Ok(Some(device.create_element(None)?))
+            }
+        }
+    }
+}
+
+/// Describes the `audio-filter` of `playbin`, or an empty string if no filter is needed. Elements
+/// are only included while they're used: balancing and downmixing force stereo, and `level` and
+/// `spectrum` are part of gst-plugins-good and cost time on every buffer.
+fn audio_filter(balance: f32, downmix: bool, metering: bool, spectrum: Option<u32>) -> String {
+    let mut filter = Vec::new();
+    if downmix {
+        filter.push("audioconvert ! audio/x-raw,channels=2".to_string());
+    }
+    if balance != 0.0 {
+        filter.push(format!(
+            "audioconvert ! audiopanorama name=iced_panorama panorama={balance}"
+        ));
+    }
+    if metering {
+        filter.push("level name=iced_level interval=50000000".to_string());
+    }
+    if let Some(bands) = spectrum {
+        filter.push(format!(
+            "spectrum name=iced_spectrum bands={bands} interval=50000000"
+        ));
+    }
+    filter.join(" ! ")
+}
+
+/// Identifies a device by the first of the properties which device providers set to tell their
+/// devices apart (e.g., PipeWire's `object.path`), falling back to its display name.
+fn device_id(device: &gst::Device) -> String {
+    device
+        .properties()
+        .and_then(|properties| {
+            [
+                "object.path",
+                "node.name",
+                "device.id",
+                "device.path",
+                "device.bus_path",
+            ]
+            .into_iter()
+            .find_map(|key| properties.get::<String>(key).ok())
+        })
+        .unwrap_or_else(|| device.display_name().to_string())
+}
+
+fn audio_devices() -> Result<Vec<gst::Device>, Error> {
+    gst::init()?;
+    let monitor = gst::DeviceMonitor::new();
+    monitor.add_filter(Some("Audio/Sink"), None);
+    monitor.start()?;
+    let devices = monitor.devices().into_iter().collect();
+    monitor.stop();
+    Ok(devices)
+}
+
 impl From<Duration> for Position {
     fn from(t: Duration) -> Self {
         Position::Time(t)
@@ -42,7 +217,7 @@ impl From<u64> for Position {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Frame(gst::Sample);
 
 impl Frame {
@@ -63,6 +238,18 @@ impl Frame {
                 .map(|meta| meta.stride()[0] as u32)
         })
     }
+
+    /// Get the byte offset of the alpha plane of an AV12 frame, which follows the Y and UV planes.
+    pub fn alpha_offset(&self, width: u32, height: u32) -> usize {
+        self.0
+            .buffer()
+            .and_then(|buffer| buffer.meta::<VideoMeta>())
+            .and_then(|meta| meta.offset().get(2).copied())
+            .unwrap_or_else(|| {
+                let stride = self.stride().unwrap_or(width) as usize;
+                stride * height as usize + stride * height.div_ceil(2) as usize
+            })
+    }
 }
 
 #[derive(Debug)]
@@ -77,6 +264,36 @@ pub(crate) struct Internal {
     pub(crate) width: i32,
     pub(crate) height: i32,
     pub(crate) framerate: f64,
+    /// Frames are AV12, carrying an alpha plane after the NV12 planes.
+    pub(crate) alpha: bool,
+    /// Width of a pixel relative to its height, for anamorphic video.
+    pub(crate) pixel_aspect_ratio: f32,
+    /// Optional `deinterlace` element named `iced_deinterlace`.
+    pub(crate) deinterlace: Option<gst::Element>,
+    pub(crate) deinterlace_mode: DeinterlaceMode,
+    /// Pad carrying the video before deinterlacing, whose caps tell if the stream is interlaced.
+    pub(crate) interlace_pad: gst::Pad,
+    /// Optional `level` element named `iced_level`.
+    pub(crate) level: Option<gst::Element>,
+    /// Optional `spectrum` element named `iced_spectrum`.
+    pub(crate) spectrum: Option<gst::Element>,
+    /// Last levels posted by `level` and `spectrum`, if either is enabled.
+    pub(crate) audio_levels: Option<AudioLevels>,
+    /// Whether the loudness of each channel is measured.
+    pub(crate) audio_metering: bool,
+    /// Number of bands of the audio spectrum, if it's measured.
+    pub(crate) audio_spectrum: Option<u32>,
+    /// Optional `audiopanorama` element named `iced_panorama`, only present while the audio is
+    /// balanced.
+    pub(crate) panorama: Option<gst::Element>,
+    pub(crate) audio_balance: f32,
+    pub(crate) stereo_downmix: bool,
+    /// Delay of the audio set by the user in nanoseconds, on top of the measured latency.
+    pub(crate) audio_delay: i64,
+    /// Decoding of the audio peaks, started by the first waveform.
+    pub(crate) waveform: Mutex<Option<Arc<waveform::Decode>>>,
+    pub(crate) audio_output: AudioOutput,
+    pub(crate) aspect_ratio_override: Option<f32>,
     pub(crate) duration: Duration,
     pub(crate) speed: f64,
     pub(crate) sync_av: bool,
@@ -84,6 +301,11 @@ pub(crate) struct Internal {
     pub(crate) frame: Arc<Mutex<Frame>>,
     pub(crate) upload_frame: Arc<AtomicBool>,
     pub(crate) last_frame_time: Arc<Mutex<Instant>>,
+    /// Region of the frame to display, in pixels.
+    pub(crate) crop: Option<iced::Rectangle<u32>>,
+    /// Last frame converted on the CPU, for renderers without primitive support, and whether
+    /// subtitles were composited onto it.
+    pub(crate) cpu_frame: Arc<Mutex<Option<(img::Handle, bool)>>>,
     pub(crate) looping: bool,
     pub(crate) is_eos: bool,
     pub(crate) restart_stream: bool,
@@ -91,7 +313,21 @@ pub(crate) struct Internal {
     pub(crate) sync_av_counter: u64,
 
     pub(crate) subtitle_text: Arc<Mutex<Option<String>>>,
+    /// Cues active at the last frame.
+    pub(crate) subtitle_cues: Arc<Mutex<Vec<SubtitleCue>>>,
     pub(crate) upload_text: Arc<AtomicBool>,
+    /// Image-based subtitles shown at the last frame.
+    pub(crate) subtitle_bitmap: Arc<Mutex<Option<Bitmap>>>,
+    pub(crate) upload_bitmap: Arc<AtomicBool>,
+    /// Tells the worker to drop the cues it pulled, e.g., after a seek.
+    pub(crate) flush_subtitles: Arc<AtomicBool>,
+    pub(crate) text_sink: Option<gst_app::AppSink>,
+    /// Shift of subtitles relative to the video in nanoseconds, positive when they're shown later.
+    pub(crate) subtitle_delay: Arc<AtomicI64>,
+    /// Cues loaded from memory, ordered by start time, shown instead of those of the stream.
+    pub(crate) loaded_subtitles: Arc<Mutex<Vec<SubtitleCue>>>,
+    /// Whether closed captions embedded in the video stream are decoded.
+    pub(crate) closed_captions: Arc<AtomicBool>,
 }
 
 impl Internal {
@@ -129,11 +365,66 @@ impl Internal {
         };
 
         *self.subtitle_text.lock().expect("lock subtitle_text") = None;
+        self.subtitle_cues
+            .lock()
+            .expect("lock subtitle_cues")
+            .clear();
+        self.flush_subtitles.store(true, Ordering::SeqCst);
         self.upload_text.store(true, Ordering::SeqCst);
+        *self.subtitle_bitmap.lock().expect("lock subtitle_bitmap") = None;
+        self.upload_bitmap.store(true, Ordering::SeqCst);
 
         Ok(())
     }
 
+    /// Updates the audio levels from an element message, returning whether it was posted by
+    /// `level` or `spectrum`.
+    pub(crate) fn update_audio_levels(&mut self, structure: &gst::StructureRef) -> bool {
+        let channels = |field| {
+            structure
+                .get::<glib::ValueArray>(field)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|value| value.get::<f64>().ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        if structure.has_name("level") {
+            let levels = self.audio_levels.get_or_insert_with(AudioLevels::default);
+            levels.rms = channels("rms");
+            levels.peak = channels("peak");
+        } else if structure.has_name("spectrum") {
+            let levels = self.audio_levels.get_or_insert_with(AudioLevels::default);
+            levels.spectrum = structure
+                .get::<gst::List>("magnitude")
+                .map(|magnitudes| {
+                    magnitudes
+                        .iter()
+                        .filter_map(|magnitude| magnitude.get::<f32>().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Forgets the audio levels once neither `level` nor `spectrum` posts them.
+    fn clear_audio_levels(&mut self) {
+        let posting = |element: &Option<gst::Element>| {
+            element
+                .as_ref()
+                .is_some_and(|element| element.property::<bool>("post-messages"))
+        };
+        if !posting(&self.level) && !posting(&self.spectrum) {
+            self.audio_levels = None;
+        }
+    }
+
     pub(crate) fn set_speed(&mut self, speed: f64) -> Result<(), Error> {
         let Some(position) = self.source.query_position::<gst::ClockTime>() else {
             return Err(Error::Caps);
@@ -183,10 +474,80 @@ impl Internal {
         }
     }
 
+    /// Sets a property in the `Ready` state, which some `playbin` properties (e.g., `audio-sink`)
+    /// require, then restores the playback state and position. Pipelines without the property
+    /// (i.e., other than `playbin`) are left as they are.
+    pub(crate) fn reconfigure(&mut self, property: &str, value: impl ToValue) -> Result<(), Error> {
+        if !self.source.has_property(property, None) {
+            return Ok(());
+        }
+        let paused = self.paused();
+        let position = self.source.query_position::<gst::ClockTime>();
+
+        self.source.set_state(gst::State::Ready)?;
+        self.source.set_property(property, value.to_value());
+        self.set_paused(paused);
+
+        // the pipeline starts over, so seek back once it's ready
+        self.source.state(gst::ClockTime::from_seconds(5)).0?;
+        if let Some(position) = position {
+            self.seek(Duration::from_nanos(position.nseconds()), true)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn paused(&self) -> bool {
         self.source.state(gst::ClockTime::ZERO).1 == gst::State::Paused
     }
 
+    /// The displayed region of the frame in pixels, i.e., the crop region clamped to the frame.
+    pub(crate) fn crop_rect(&self) -> iced::Rectangle<u32> {
+        let (width, height) = (self.width as u32, self.height as u32);
+        match self.crop {
+            Some(crop) => {
+                let x = crop.x.min(width.saturating_sub(1));
+                let y = crop.y.min(height.saturating_sub(1));
+                iced::Rectangle {
+                    x,
+                    y,
+                    width: crop.width.min(width - x).max(1),
+                    height: crop.height.min(height - y).max(1),
+                }
+            }
+            None => iced::Rectangle {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+        }
+    }
+
+    /// The displayed region of the frame in normalized texture coordinates.
+    pub(crate) fn crop_uv(&self) -> iced::Rectangle {
+        let crop = self.crop_rect();
+        let (width, height) = (self.width as f32, self.height as f32);
+        iced::Rectangle {
+            x: crop.x as f32 / width,
+            y: crop.y as f32 / height,
+            width: crop.width as f32 / width,
+            height: crop.height as f32 / height,
+        }
+    }
+
+    /// Size of the displayed video in pixels, before it's fit into the widget.
+    ///
+    /// Non-square pixels (or an aspect ratio override) stretch the width, keeping the height.
+    pub(crate) fn display_size(&self) -> iced::Size {
+        let crop = self.crop_rect();
+        let height = crop.height as f32;
+        let width = match self.aspect_ratio_override {
+            Some(aspect_ratio) => height * aspect_ratio,
+            None => crop.width as f32 * self.pixel_aspect_ratio,
+        };
+        iced::Size::new(width, height)
+    }
+
     /// Syncs audio with video when there is (inevitably) latency presenting the frame.
     pub(crate) fn set_av_offset(&mut self, offset: Duration) {
         if self.sync_av {
@@ -194,11 +555,46 @@ impl Internal {
             self.sync_av_avg = self.sync_av_avg * (self.sync_av_counter - 1) / self.sync_av_counter
                 + offset.as_nanos() as u64 / self.sync_av_counter;
             if self.sync_av_counter % 128 == 0 {
-                self.source
-                    .set_property("av-offset", -(self.sync_av_avg as i64));
+                self.apply_av_offset();
             }
         }
     }
+
+    /// Sets `av-offset` from the measured latency and the delay set by the user, where negative
+    /// offsets delay the audio.
+    fn apply_av_offset(&self) {
+        self.source.set_property(
+            "av-offset",
+            (-(self.sync_av_avg as i64)).saturating_sub(self.audio_delay),
+        );
+    }
+
+    /// Replaces the audio filter of `playbin` with one applying the balance and downmix, and
+    /// measuring the audio levels which are enabled.
+    fn rebuild_audio_filter(&mut self) -> Result<(), Error> {
+        if !self.source.has_property("audio-filter", None) {
+            return Ok(());
+        }
+
+        let description = audio_filter(
+            self.audio_balance,
+            self.stereo_downmix,
+            self.audio_metering,
+            self.audio_spectrum,
+        );
+        let filter = if description.is_empty() {
+            None
+        } else {
+            Some(gst::parse::bin_from_description(&description, true)?)
+        };
+
+        self.reconfigure("audio-filter", &filter)?;
+        let by_name = |name| filter.as_ref().and_then(|filter| filter.by_name(name));
+        self.level = by_name("iced_level");
+        self.spectrum = by_name("iced_spectrum");
+        self.panorama = by_name("iced_panorama");
+        Ok(())
+    }
 }
 
 /// A multimedia video loaded from a URI (e.g., a local file path or HTTP stream).
@@ -223,6 +619,10 @@ impl Drop for Video {
                 }
             }
         }
+
+        if let Ok(Some(decode)) = inner.waveform.get_mut() {
+            decode.cancel();
+        }
     }
 }
 
@@ -230,10 +630,29 @@ impl Video {
     /// Create a new video player from a given video which loads from `uri`.
     /// Note that live sources will report the duration to be zero.
     pub fn new(uri: &url::Url) -> Result<Self, Error> {
+        Self::from_uri(uri, "NV12")
+    }
+
+    /// Create a new video player like [`Video::new`], which keeps the alpha channel of the video.
+    ///
+    /// Transparent video (e.g., VP8/VP9 WebM with alpha, ProRes 4444) is then blended over whatever
+    /// is drawn behind the player. Opaque video plays as usual, at the cost of uploading an extra plane.
+    /// Requires GStreamer 1.22 or newer, for the `AV12` format.
+    pub fn new_with_alpha(uri: &url::Url) -> Result<Self, Error> {
+        Self::from_uri(uri, "AV12")
+    }
+
+    fn from_uri(uri: &url::Url, format: &str) -> Result<Self, Error> {
         gst::init()?;
 
+        // `deinterlace` is part of gst-plugins-good, which may not be installed
+        let deinterlace = if gst::ElementFactory::find("deinterlace").is_some() {
+            "deinterlace name=iced_deinterlace ! "
+        } else {
+            ""
+        };
         let pipeline = format!(
-            "playbin uri=\"{}\" text-sink=\"appsink name=iced_text sync=true drop=true\" video-sink=\"videoscale ! videoconvert ! appsink name=iced_video drop=true caps=video/x-raw,format=NV12,pixel-aspect-ratio=1/1\"",
+            "playbin uri=\"{}\" text-sink=\"appsink name=iced_text sync=true drop=true\" video-sink=\"{deinterlace}videoconvert ! appsink name=iced_video drop=true caps=video/x-raw,format={format}\"",
             uri.as_str()
         );
         let pipeline = gst::parse::launch(pipeline.as_ref())?
@@ -258,10 +677,12 @@ impl Video {
     }
 
     /// Creates a new video based on an existing GStreamer pipeline and appsink.
-    /// Expects an `appsink` plugin with `caps=video/x-raw,format=NV12`.
+    /// Expects an `appsink` plugin with `caps=video/x-raw,format=NV12`, or `format=AV12` to keep the alpha channel.
     ///
     /// An optional `text_sink` can be provided, which enables subtitle messages
-    /// to be emitted.
+    /// to be emitted. Image-based subtitles are shown when they reach it decoded as raw video
+    /// (`RGBA`, `BGRA`, `ARGB` or `AYUV`), or when overlay elements such as `dvdspu` or `dvbsuboverlay`
+    /// attach them to the frames of `video_sink` as overlay compositions.
     ///
     /// **Note:** Many functions of [`Video`] assume a `playbin` pipeline.
     /// Non-`playbin` pipelines given here may not have full functionality.
@@ -287,6 +708,16 @@ impl Video {
 
         let pad = video_sink.pads().first().cloned().unwrap();
 
+        let deinterlace = pipeline.by_name("iced_deinterlace");
+        let deinterlace_mode = DeinterlaceMode::default();
+        if let Some(deinterlace) = &deinterlace {
+            deinterlace.set_property_from_str("mode", deinterlace_mode.as_gst_mode());
+        }
+        let interlace_pad = deinterlace
+            .as_ref()
+            .and_then(|deinterlace| deinterlace.static_pad("sink"))
+            .unwrap_or_else(|| pad.clone());
+
         cleanup!(pipeline.set_state(gst::State::Playing))?;
 
         // wait for up to 5 seconds until the decoder gets the source capabilities
@@ -300,6 +731,13 @@ impl Video {
         let height = cleanup!(s.get::<i32>("height").map_err(|_| Error::Caps))?;
         let framerate = cleanup!(s.get::<gst::Fraction>("framerate").map_err(|_| Error::Caps))?;
         let framerate = framerate.numer() as f64 / framerate.denom() as f64;
+        let alpha = s.get::<&str>("format").is_ok_and(|format| format == "AV12");
+        // frames are uploaded at their native resolution, so non-square pixels are stretched when drawn
+        let pixel_aspect_ratio = s
+            .get::<gst::Fraction>("pixel-aspect-ratio")
+            .ok()
+            .filter(|par| par.numer() > 0 && par.denom() > 0)
+            .map_or(1.0, |par| par.numer() as f32 / par.denom() as f32);
 
         if framerate.is_nan()
             || framerate.is_infinite()
@@ -310,6 +748,11 @@ impl Video {
             return Err(Error::Framerate(framerate));
         }
 
+        // the audio filter is only added along with the audio sink, once the streams are known
+        let level = pipeline.by_name("iced_level");
+        let spectrum = pipeline.by_name("iced_spectrum");
+        let panorama = pipeline.by_name("iced_panorama");
+
         let duration = Duration::from_nanos(
             pipeline
                 .query_duration::<gst::ClockTime>()
@@ -331,14 +774,41 @@ impl Video {
         let last_frame_time_ref = Arc::clone(&last_frame_time);
 
         let subtitle_text = Arc::new(Mutex::new(None));
+        let subtitle_cues = Arc::new(Mutex::new(Vec::new()));
         let upload_text = Arc::new(AtomicBool::new(false));
+        let flush_subtitles = Arc::new(AtomicBool::new(false));
         let subtitle_text_ref = Arc::clone(&subtitle_text);
+        let subtitle_cues_ref = Arc::clone(&subtitle_cues);
         let upload_text_ref = Arc::clone(&upload_text);
+        let flush_subtitles_ref = Arc::clone(&flush_subtitles);
+        let subtitle_bitmap = Arc::new(Mutex::new(None));
+        let upload_bitmap = Arc::new(AtomicBool::new(false));
+        let subtitle_bitmap_ref = Arc::clone(&subtitle_bitmap);
+        let upload_bitmap_ref = Arc::clone(&upload_bitmap);
+        let subtitle_delay = Arc::new(AtomicI64::new(0));
+        let subtitle_delay_ref = Arc::clone(&subtitle_delay);
+        let text_sink_ref = text_sink.clone();
+        let loaded_subtitles: Arc<Mutex<Vec<SubtitleCue>>> = Arc::new(Mutex::new(Vec::new()));
+        let loaded_subtitles_ref = Arc::clone(&loaded_subtitles);
+        let closed_captions = Arc::new(AtomicBool::new(false));
+        let closed_captions_ref = Arc::clone(&closed_captions);
 
         let pipeline_ref = pipeline.clone();
 
         let worker = std::thread::spawn(move || {
-            let mut clear_subtitles_at = None;
+            // cues pulled from the text sink along with their running times and original text,
+            // kept until they end
+            let mut pending: Vec<(i64, i64, String, SubtitleCue)> = Vec::new();
+            let mut script = None;
+            // image-based subtitles pulled from the text sink, kept until they end or are replaced
+            let mut pending_bitmap: Option<(i64, Option<i64>, Bitmap)> = None;
+            let mut bitmap_shown = false;
+            let mut bitmap_replaced = false;
+            // sequence number of the overlay composition attached to the last frame
+            let mut composition = None;
+            // closed captions decoded from the frames, shown until they're replaced
+            let mut decoder = cea608::Decoder::default();
+            let mut caption: Option<SubtitleCue> = None;
 
             while alive_ref.load(Ordering::Acquire) {
                 if let Err(gst::FlowError::Error) = (|| -> Result<(), gst::FlowError> {
@@ -361,6 +831,36 @@ impl Video {
                     let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
                     let frame_pts = buffer.pts().ok_or(gst::FlowError::Error)?;
                     let frame_duration = buffer.duration().ok_or(gst::FlowError::Error)?;
+
+                    // overlay elements upstream (e.g., `dvdspu` or `dvbsuboverlay`) may attach
+                    // their subtitles to the frame instead of blending them in
+                    let frame_composition = buffer.meta::<VideoOverlayCompositionMeta>();
+                    let seqnum = frame_composition
+                        .as_ref()
+                        .map(|meta| meta.overlay().seqnum());
+                    if seqnum != composition {
+                        composition = seqnum;
+                        *subtitle_bitmap_ref
+                            .lock()
+                            .map_err(|_| gst::FlowError::Error)? =
+                            frame_composition.and_then(|meta| {
+                                composition_bitmap(meta.overlay(), width as _, height as _)
+                            });
+                        upload_bitmap_ref.store(true, Ordering::SeqCst);
+                        bitmap_shown = false;
+                    }
+
+                    // closed captions are attached to the frames, but decoded after any flush
+                    let decode_captions = closed_captions_ref.load(Ordering::Relaxed);
+                    let caption_data: Vec<_> = if decode_captions {
+                        buffer
+                            .iter_meta::<VideoCaptionMeta>()
+                            .map(|meta| (meta.caption_type(), meta.data().to_vec()))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+
                     {
                         let mut frame_guard =
                             frame_ref.lock().map_err(|_| gst::FlowError::Error)?;
@@ -369,52 +869,129 @@ impl Video {
 
                     upload_frame_ref.swap(true, Ordering::SeqCst);
 
-                    if let Some(at) = clear_subtitles_at {
-                        if frame_pts >= at {
-                            *subtitle_text_ref
-                                .lock()
-                                .map_err(|_| gst::FlowError::Error)? = None;
-                            upload_text_ref.store(true, Ordering::SeqCst);
-                            clear_subtitles_at = None;
+                    if flush_subtitles_ref.swap(false, Ordering::SeqCst) {
+                        pending.clear();
+                        pending_bitmap = None;
+                        decoder.reset();
+                        caption = None;
+                    }
+
+                    if decode_captions {
+                        let start = Duration::from_nanos(frame_pts.nseconds());
+                        for (caption_type, data) in caption_data {
+                            if let Some(cue) = decoder.decode(caption_type, &data, start) {
+                                caption = cue;
+                            }
                         }
+                    } else if caption.is_some() {
+                        decoder.reset();
+                        caption = None;
                     }
 
-                    let text = text_sink
+                    // overlapping cues arrive as separate buffers, so take all of them
+                    while let Some(text) = text_sink_ref
                         .as_ref()
-                        .and_then(|sink| sink.try_pull_sample(gst::ClockTime::from_seconds(0)));
-                    if let Some(text) = text {
+                        .and_then(|sink| sink.try_pull_sample(gst::ClockTime::from_seconds(0)))
+                    {
                         let text_segment = text.segment().ok_or(gst::FlowError::Error)?;
-                        let text = text.buffer().ok_or(gst::FlowError::Error)?;
-                        let text_pts = text.pts().ok_or(gst::FlowError::Error)?;
-                        let text_duration = text.duration().ok_or(gst::FlowError::Error)?;
+                        let buffer = text.buffer().ok_or(gst::FlowError::Error)?;
+                        let text_pts = buffer.pts().ok_or(gst::FlowError::Error)?;
+                        let text_running_time = text_segment.to_running_time(text_pts).value();
 
-                        let frame_running_time = frame_segment.to_running_time(frame_pts).value();
-                        let frame_running_time_end = frame_segment
-                            .to_running_time(frame_pts + frame_duration)
-                            .value();
+                        if let Some(bitmap) = parse_bitmap(&text)? {
+                            // image-based subtitles often last until the next one replaces them
+                            let end = buffer.duration().map(|duration| {
+                                text_segment.to_running_time(text_pts + duration).value()
+                            });
+                            pending_bitmap = Some((text_running_time, end, bitmap));
+                            bitmap_replaced = true;
+                            continue;
+                        }
+
+                        let text_duration = buffer.duration().ok_or(gst::FlowError::Error)?;
+                        let Some((raw, cue)) = parse_cue(&text, &mut script)? else {
+                            continue;
+                        };
+                        let start = Duration::from_nanos(text_pts.nseconds());
+                        let cue = SubtitleCue {
+                            start,
+                            end: start + Duration::from_nanos(text_duration.nseconds()),
+                            ..cue
+                        };
 
-                        let text_running_time = text_segment.to_running_time(text_pts).value();
                         let text_running_time_end = text_segment
                             .to_running_time(text_pts + text_duration)
                             .value();
+                        pending.push((text_running_time, text_running_time_end, raw, cue));
+                    }
 
-                        // see gst-plugins-base/ext/pango/gstbasetextoverlay.c (gst_base_text_overlay_video_chain)
-                        // as an example of how to correctly synchronize the text+video segments
-                        if text_running_time_end > frame_running_time
-                            && frame_running_time_end > text_running_time
-                        {
-                            let duration = text.duration().unwrap_or(gst::ClockTime::ZERO);
-                            let map = text.map_readable().map_err(|_| gst::FlowError::Error)?;
-
-                            let text = std::str::from_utf8(map.as_slice())
-                                .map_err(|_| gst::FlowError::Error)?
-                                .to_string();
-                            *subtitle_text_ref
-                                .lock()
-                                .map_err(|_| gst::FlowError::Error)? = Some(text);
-                            upload_text_ref.store(true, Ordering::SeqCst);
+                    // delayed subtitles overlap earlier frames, so compare them with shifted frames
+                    let delay = subtitle_delay_ref.load(Ordering::Relaxed);
+                    let frame_running_time = frame_segment
+                        .to_running_time(frame_pts)
+                        .value()
+                        .saturating_sub(delay);
+                    let frame_running_time_end = frame_segment
+                        .to_running_time(frame_pts + frame_duration)
+                        .value()
+                        .saturating_sub(delay);
+
+                    // see gst-plugins-base/ext/pango/gstbasetextoverlay.c (gst_base_text_overlay_video_chain)
+                    // as an example of how to correctly synchronize the text+video segments
+                    pending.retain(|(_, end, _, _)| *end > frame_running_time);
+                    let loaded = loaded_subtitles_ref
+                        .lock()
+                        .map_err(|_| gst::FlowError::Error)?;
+                    // cues along with the text given to `on_subtitle_text`
+                    let mut active: Vec<_> = if loaded.is_empty() {
+                        pending
+                            .iter()
+                            .filter(|(start, _, _, _)| frame_running_time_end > *start)
+                            .map(|(_, _, raw, cue)| (raw.clone(), cue.clone()))
+                            .collect()
+                    } else {
+                        // loaded cues are timed by the position in the media
+                        loaded_cues(
+                            &loaded,
+                            frame_segment.to_stream_time(frame_pts).value(),
+                            frame_duration.nseconds(),
+                            delay,
+                        )
+                        .map(|cue| (cue.text(), cue.clone()))
+                        .collect()
+                    };
+                    drop(loaded);
+                    active.extend(caption.clone().map(|cue| (cue.text(), cue)));
+                    let (texts, active): (Vec<_>, Vec<_>) = active.into_iter().unzip();
+
+                    let mut cues = subtitle_cues_ref
+                        .lock()
+                        .map_err(|_| gst::FlowError::Error)?;
+                    if *cues != active {
+                        *subtitle_text_ref
+                            .lock()
+                            .map_err(|_| gst::FlowError::Error)? =
+                            Some(texts.join("\n")).filter(|text| !text.is_empty());
+                        *cues = active;
+                        upload_text_ref.store(true, Ordering::SeqCst);
+                    }
 
-                            clear_subtitles_at = Some(text_pts + duration);
+                    // subtitles attached to the frame take precedence
+                    if composition.is_none() {
+                        pending_bitmap.take_if(|(_, end, _)| {
+                            end.is_some_and(|end| end <= frame_running_time)
+                        });
+                        let active = pending_bitmap
+                            .as_ref()
+                            .filter(|(start, _, _)| frame_running_time_end > *start);
+                        if bitmap_replaced || active.is_some() != bitmap_shown {
+                            bitmap_replaced = false;
+                            bitmap_shown = active.is_some();
+                            *subtitle_bitmap_ref
+                                .lock()
+                                .map_err(|_| gst::FlowError::Error)? =
+                                active.map(|(_, _, bitmap)| bitmap.clone());
+                            upload_bitmap_ref.store(true, Ordering::SeqCst);
                         }
                     }
 
@@ -436,6 +1013,23 @@ impl Video {
             width,
             height,
             framerate,
+            alpha,
+            pixel_aspect_ratio,
+            aspect_ratio_override: None,
+            deinterlace,
+            deinterlace_mode,
+            interlace_pad,
+            level,
+            spectrum,
+            audio_levels: None,
+            audio_metering: false,
+            audio_spectrum: None,
+            panorama,
+            audio_balance: 0.0,
+            stereo_downmix: false,
+            audio_delay: 0,
+            waveform: Mutex::new(None),
+            audio_output: AudioOutput::Default,
             duration,
             speed: 1.0,
             sync_av,
@@ -443,6 +1037,8 @@ impl Video {
             frame,
             upload_frame,
             last_frame_time,
+            crop: None,
+            cpu_frame: Arc::new(Mutex::new(None)),
             looping: false,
             is_eos: false,
             restart_stream: false,
@@ -450,7 +1046,15 @@ impl Video {
             sync_av_counter: 0,
 
             subtitle_text,
+            subtitle_cues,
             upload_text,
+            subtitle_bitmap,
+            upload_bitmap,
+            flush_subtitles,
+            text_sink,
+            subtitle_delay,
+            loaded_subtitles,
+            closed_captions,
         })))
     }
 
@@ -476,6 +1080,63 @@ impl Video {
         self.read().framerate
     }
 
+    /// Get if frames keep their alpha channel, i.e., the video was created with [`Video::new_with_alpha`]
+    /// or its appsink negotiated `AV12`.
+    pub fn has_alpha(&self) -> bool {
+        self.read().alpha
+    }
+
+    /// Get the pixel aspect ratio of the video (the width of a pixel relative to its height).
+    /// This is `1.0` unless the video is anamorphic.
+    pub fn pixel_aspect_ratio(&self) -> f32 {
+        self.read().pixel_aspect_ratio
+    }
+
+    /// Overrides the display aspect ratio (width / height) of the video, e.g., `16.0 / 9.0` or `2.39`.
+    /// Passing `None` restores the aspect ratio of the stream, including its pixel aspect ratio.
+    ///
+    /// The aspect ratio applies to the cropped region if a crop is set.
+    pub fn set_aspect_ratio_override(&mut self, aspect_ratio: Option<f32>) {
+        self.get_mut().aspect_ratio_override =
+            aspect_ratio.filter(|aspect_ratio| aspect_ratio.is_finite() && *aspect_ratio > 0.0);
+    }
+
+    /// Get the display aspect ratio override, if any.
+    pub fn aspect_ratio_override(&self) -> Option<f32> {
+        self.read().aspect_ratio_override
+    }
+
+    /// Set how interlaced video is deinterlaced. The default is [`DeinterlaceMode::Auto`].
+    ///
+    /// Has no effect without the `deinterlace` element of gst-plugins-good, or on pipelines from
+    /// [`Video::from_gst_pipeline`] without a `deinterlace` element named `iced_deinterlace`.
+    pub fn set_deinterlace(&mut self, mode: DeinterlaceMode) {
+        let mut inner = self.get_mut();
+        if let Some(deinterlace) = &inner.deinterlace {
+            deinterlace.set_property_from_str("mode", mode.as_gst_mode());
+        }
+        inner.deinterlace_mode = mode;
+    }
+
+    /// Get how interlaced video is deinterlaced.
+    pub fn deinterlace(&self) -> DeinterlaceMode {
+        self.read().deinterlace_mode
+    }
+
+    /// Get if the decoded stream is flagged as interlaced (or mixes interlaced and progressive frames).
+    pub fn interlaced(&self) -> bool {
+        self.read()
+            .interlace_pad
+            .current_caps()
+            .and_then(|caps| {
+                caps.structure(0)?
+                    .get::<String>("interlace-mode")
+                    .ok()
+                    .map(|mode| mode != "progressive")
+            })
+            .unwrap_or(false)
+    }
+
     /// Set the volume multiplier of the audio.
     /// `0.0` = 0% volume, `1.0` = 100% volume.
     ///
@@ -500,6 +1161,182 @@ impl Video {
         self.read().source.property("mute")
     }
 
+    /// Set whether the loudness of each audio channel is measured, e.g., for VU meters.
+    /// Read it with [`audio_levels`](Self::audio_levels) or receive it with
+    /// [`VideoPlayer::on_audio_levels`](crate::VideoPlayer::on_audio_levels).
+    ///
+    /// Turning metering on or off briefly stops the pipeline to add or remove a `level` element in
+    /// the `audio-filter` of `playbin`, resuming at the same position. Has no effect on pipelines
+    /// from [`Video::from_gst_pipeline`] without a `level` element named `iced_level`.
+    pub fn set_audio_metering(&mut self, enabled: bool) -> Result<(), Error> {
+        let mut inner = self.get_mut();
+        let rebuild = inner.audio_metering != enabled;
+        inner.audio_metering = enabled;
+        if rebuild {
+            inner.rebuild_audio_filter()?;
+        }
+        // the elements of custom pipelines stay in place, only posting while enabled
+        if let Some(level) = &inner.level {
+            level.set_property("post-messages", enabled);
+        }
+        if !enabled && let Some(levels) = &mut inner.audio_levels {
+            levels.rms.clear();
+            levels.peak.clear();
+        }
+        inner.clear_audio_levels();
+        Ok(())
+    }
+
+    /// Set the number of frequency bands of the audio spectrum to measure, at least `2`, or `None`
+    /// to stop measuring it. The bands evenly divide the frequencies up to half the sample rate.
+    ///
+    /// Turning the spectrum on or off briefly stops the pipeline to add or remove a `spectrum`
+    /// element in the `audio-filter` of `playbin`, resuming at the same position. Has no effect on
+    /// pipelines from [`Video::from_gst_pipeline`] without a `spectrum` element named `iced_spectrum`.
+    pub fn set_audio_spectrum(&mut self, bands: Option<u32>) -> Result<(), Error> {
+        let bands = bands.map(|bands| bands.max(2));
+        let mut inner = self.get_mut();
+        let rebuild = inner.audio_spectrum.is_some() != bands.is_some();
+        inner.audio_spectrum = bands;
+        if rebuild {
+            inner.rebuild_audio_filter()?;
+        }
+        if let Some(spectrum) = &inner.spectrum {
+            if let Some(bands) = bands {
+                spectrum.set_property("bands", bands);
+            }
+            spectrum.set_property("post-messages", bands.is_some());
+        }
+        if bands.is_none()
+            && let Some(levels) = &mut inner.audio_levels
+        {
+            levels.spectrum.clear();
+        }
+        inner.clear_audio_levels();
+        Ok(())
+    }
+
+    /// Get the last audio levels measured, if [`set_audio_metering`](Self::set_audio_metering) or
+    /// [`set_audio_spectrum`](Self::set_audio_spectrum) enabled them.
+    ///
+    /// Levels are read from the messages of the pipeline while a [`VideoPlayer`](crate::VideoPlayer)
+    /// displays the video.
+    pub fn audio_levels(&self) -> Option<AudioLevels> {
+        self.read().audio_levels.clone()
+    }
+
+    /// Decodes the audio track on a pipeline of its own, leaving playback untouched, and returns
+    /// the lowest and highest sample of each of `buckets` equal slices of the track, in `-1.0..=1.0`,
+    /// e.g., to draw a waveform along a seek bar with [`iced::Task::perform`].
+    ///
+    /// The track is only decoded once, so waveforms of other widths are quick.
+    /// Fails with [`Error::Uri`] for pipelines from [`Video::from_gst_pipeline`] other than `playbin`.
+    pub fn waveform(
+        &self,
+        buckets: usize,
+    ) -> impl Future<Output = Result<Vec<(f32, f32)>, Error>> + Send + 'static {
+        let decode = (|| -> Result<_, Error> {
+            let inner = self.read();
+            let mut cached = inner.waveform.lock().map_err(|_| Error::Lock)?;
+            if let Some(decode) = cached.as_ref().filter(|decode| !decode.failed()) {
+                return Ok(Arc::clone(decode));
+            }
+
+            let uri = inner
+                .source
+                .has_property("current-uri", None)
+                .then(|| inner.source.property::<Option<String>>("current-uri"))
+                .flatten()
+                .ok_or(Error::Uri)?;
+            let decode = waveform::Decode::start(uri);
+            *cached = Some(Arc::clone(&decode));
+            Ok(decode)
+        })();
+
+        async move {
+            let peaks = decode?.peaks().await?;
+            Ok(waveform::buckets(&peaks, buckets))
+        }
+    }
+
+    /// Set where the audio is played, e.g., a specific device from [`AudioOutput::devices`].
+    ///
+    /// The pipeline is briefly stopped to swap the sink, resuming at the same position.
+    /// Only applies to `playbin` pipelines.
+    pub fn set_audio_output(&mut self, output: AudioOutput) -> Result<(), Error> {
+        let sink = output.sink()?;
+        let mut inner = self.get_mut();
+        inner.reconfigure("audio-sink", sink)?;
+        inner.audio_output = output;
+        Ok(())
+    }
+
+    /// Get where the audio is played.
+    pub fn audio_output(&self) -> AudioOutput {
+        self.read().audio_output.clone()
+    }
+
+    /// Set the balance of the audio between the left (`-1.0`) and right (`1.0`) speakers,
+    /// or `0.0` to leave it as is. Balanced audio is always stereo.
+    ///
+    /// Moving the balance away from or back to the center briefly stops the pipeline to
+    /// replace the `audio-filter` of `playbin`, resuming at the same position.
+    pub fn set_audio_balance(&mut self, balance: f32) -> Result<(), Error> {
+        let balance = balance.clamp(-1.0, 1.0);
+        let mut inner = self.get_mut();
+        let rebuild = (balance == 0.0) != (inner.audio_balance == 0.0);
+        inner.audio_balance = balance;
+        if rebuild {
+            inner.rebuild_audio_filter()
+        } else {
+            if let Some(panorama) = &inner.panorama {
+                panorama.set_property("panorama", balance);
+            }
+            Ok(())
+        }
+    }
+
+    /// Get the balance of the audio between the left (`-1.0`) and right (`1.0`) speakers.
+    pub fn audio_balance(&self) -> f32 {
+        self.read().audio_balance
+    }
+
+    /// Set whether audio with more channels (e.g., 5.1) is downmixed to stereo, e.g., for
+    /// headphones.
+    ///
+    /// Briefly stops the pipeline to replace the `audio-filter` of `playbin`, resuming at the
+    /// same position.
+    pub fn set_stereo_downmix(&mut self, downmix: bool) -> Result<(), Error> {
+        let mut inner = self.get_mut();
+        if inner.stereo_downmix == downmix {
+            return Ok(());
+        }
+        inner.stereo_downmix = downmix;
+        inner.rebuild_audio_filter()
+    }
+
+    /// Get whether audio with more channels is downmixed to stereo.
+    pub fn stereo_downmix(&self) -> bool {
+        self.read().stereo_downmix
+    }
+
+    /// Set how long the audio is played after or before the video, to fix lip-sync by hand.
+    /// Applies on top of the automatic compensation for presentation latency.
+    ///
+    /// Only applies to `playbin` pipelines.
+    pub fn set_audio_delay(&mut self, delay: Delay) {
+        let mut inner = self.get_mut();
+        inner.audio_delay = delay.as_nanos();
+        if inner.sync_av {
+            inner.apply_av_offset();
+        }
+    }
+
+    /// Get how long the audio is played after or before the video.
+    pub fn audio_delay(&self) -> Delay {
+        Delay::from_nanos(self.read().audio_delay)
+    }
+
     /// Get if the stream ended or not.
     pub fn eos(&self) -> bool {
         self.read().is_eos
@@ -584,11 +1421,158 @@ impl Video {
         .ok()
     }
 
+    /// Set how long subtitles are shown after or before their timestamps, e.g., to sync an
+    /// external subtitle file with the video.
+    pub fn set_subtitle_delay(&mut self, delay: Delay) {
+        let inner = self.get_mut();
+        let delay = delay.as_nanos();
+        inner.subtitle_delay.store(delay, Ordering::SeqCst);
+        // the text sink hands over samples in time for the shifted running time
+        if let Some(text_sink) = &inner.text_sink {
+            text_sink.set_property("ts-offset", delay);
+        }
+    }
+
+    /// Get how long subtitles are shown after or before their timestamps.
+    pub fn subtitle_delay(&self) -> Delay {
+        Delay::from_nanos(self.read().subtitle_delay.load(Ordering::SeqCst))
+    }
+
+    /// Set the character encoding of subtitle files which aren't UTF-8 (e.g., `"windows-1251"`
+    /// or `"Shift_JIS"`), as accepted by `g_convert`.
+    ///
+    /// Applies to subtitles loaded afterwards, e.g., with [`set_subtitle_url`](Self::set_subtitle_url).
+    pub fn set_subtitle_encoding(&mut self, encoding: &str) {
+        self.get_mut()
+            .source
+            .set_property("subtitle-encoding", encoding);
+    }
+
+    /// Get the character encoding of subtitle files which aren't UTF-8, if set.
+    pub fn subtitle_encoding(&self) -> Option<String> {
+        self.read()
+            .source
+            .property::<Option<String>>("subtitle-encoding")
+    }
+
+    /// Load subtitles from `text`, which are shown instead of those of the stream.
+    /// Malformed cues are skipped.
+    pub fn load_subtitles_from_str(&mut self, text: &str, format: SubtitleFormat) {
+        let cues = match format {
+            SubtitleFormat::Srt => srt::parse_file(text),
+            SubtitleFormat::Vtt => webvtt::parse_file(text),
+            SubtitleFormat::Ass => ass::parse_file(text),
+        };
+        *self
+            .read()
+            .loaded_subtitles
+            .lock()
+            .expect("lock loaded_subtitles") = cues;
+    }
+
+    /// Remove the subtitles loaded with [`load_subtitles_from_str`](Self::load_subtitles_from_str),
+    /// going back to those of the stream.
+    pub fn unload_subtitles(&mut self) {
+        self.read()
+            .loaded_subtitles
+            .lock()
+            .expect("lock loaded_subtitles")
+            .clear();
+    }
+
+    /// Get every cue loaded with [`load_subtitles_from_str`](Self::load_subtitles_from_str),
+    /// ordered by start time. Pass the start of a cue to [`seek`](Self::seek) to jump to it.
+    pub fn loaded_subtitles(&self) -> Vec<SubtitleCue> {
+        self.read()
+            .loaded_subtitles
+            .lock()
+            .expect("lock loaded_subtitles")
+            .clone()
+    }
+
+    /// Get the subtitle cues shown at the current frame, in the order they appeared.
+    pub fn subtitle_cues(&self) -> Vec<SubtitleCue> {
+        self.read()
+            .subtitle_cues
+            .lock()
+            .expect("lock subtitle_cues")
+            .clone()
+    }
+
+    /// Set whether closed captions embedded in the video stream (CEA-608, or CEA-708 carrying
+    /// CEA-608) are decoded and shown along with the other subtitles. Disabled by default.
+    ///
+    /// Captions only reach the player if the decoder or parser attaches them to the frames,
+    /// e.g., `h264parse` with MP4 and TS files.
+    pub fn set_closed_captions(&mut self, enabled: bool) {
+        self.get_mut()
+            .closed_captions
+            .store(enabled, Ordering::SeqCst);
+    }
+
+    /// Get whether closed captions embedded in the video stream are decoded.
+    pub fn closed_captions(&self) -> bool {
+        self.read().closed_captions.load(Ordering::SeqCst)
+    }
+
     /// Get the underlying GStreamer pipeline.
     pub fn pipeline(&self) -> gst::Pipeline {
         self.read().source.clone()
     }
 
+    /// Crops the displayed video to `crop`, given in pixels of the decoded frame, or shows the whole frame if `None`.
+    ///
+    /// The [`VideoPlayer`](crate::VideoPlayer) is laid out using the cropped size.
+    /// See [`Video::detect_crop`] to find a crop region which removes black bars.
+    pub fn set_crop(&mut self, crop: Option<iced::Rectangle<u32>>) {
+        self.get_mut().crop = crop;
+    }
+
+    /// Get the crop region, if any.
+    pub fn crop(&self) -> Option<iced::Rectangle<u32>> {
+        self.read().crop
+    }
+
+    /// Samples frames at a set of positions in the media and proposes a crop region which removes
+    /// letterboxing and pillarboxing, to be passed to [`Video::set_crop`].
+    ///
+    /// Luma values up to `threshold` are considered black; `24` works well for most videos.
+    /// Returns `None` if there are no black bars to remove.
+    ///
+    /// Slow, and has the same caveats as [`Video::thumbnails`].
+    /// Sample several positions, since a single dark scene may look like black bars.
+    pub fn detect_crop<I>(
+        &mut self,
+        positions: I,
+        threshold: u8,
+    ) -> Result<Option<iced::Rectangle<u32>>, Error>
+    where
+        I: IntoIterator<Item = Position>,
+    {
+        let (width, height) = self.size();
+        let full = iced::Rectangle {
+            x: 0,
+            y: 0,
+            width: width as u32,
+            height: height as u32,
+        };
+
+        let areas = self.sample_frames(positions, |frame| frame.active_area(threshold))?;
+
+        // keep anything that showed picture in any of the samples
+        let area = areas.into_iter().flatten().reduce(|a, b| {
+            let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+            iced::Rectangle {
+                x,
+                y,
+                width: (a.x + a.width).max(b.x + b.width) - x,
+                height: (a.y + a.height).max(b.y + b.height) - y,
+            }
+        });
+
+        Ok(area.filter(|area| *area != full))
+    }
+
     /// Generates a list of thumbnails based on a set of positions in the media, downscaled by a given factor.
     ///
     /// Slow; only needs to be called once for each instance.
@@ -602,7 +1586,28 @@ impl Video {
         I: IntoIterator<Item = Position>,
     {
         let downscale = u8::from(downscale) as u32;
+        let (width, height) = self.size();
+        let (width, height) = (width as u32 / downscale, height as u32 / downscale);
+
+        self.sample_frames(positions, |frame| {
+            img::Handle::from_rgba(
+                width,
+                height,
+                yuv::nv12_to_rgba(&frame, width, height, yuv::Filter::Box, yuv::Matrix::Bt601),
+            )
+        })
+    }
 
+    /// Seeks to each of `positions` in turn and passes the decoded frame to `f`,
+    /// restoring the playback state afterwards.
+    fn sample_frames<I, T>(
+        &mut self,
+        positions: I,
+        mut f: impl FnMut(yuv::Nv12<'_>) -> T,
+    ) -> Result<Vec<T>, Error>
+    where
+        I: IntoIterator<Item = Position>,
+    {
         let paused = self.paused();
         let muted = self.muted();
         let pos = self.position();
@@ -626,11 +1631,12 @@ impl Video {
                     let frame = frame_guard.readable().ok_or(Error::Lock)?;
                     let stride = frame_guard.stride();
 
-                    Ok(img::Handle::from_rgba(
-                        inner.width as u32 / downscale,
-                        inner.height as u32 / downscale,
-                        yuv_to_rgba(frame.as_slice(), width as _, height as _, downscale, stride),
-                    ))
+                    let nv12 = yuv::Nv12::new(frame.as_slice(), width as _, height as _, stride);
+                    Ok(f(if inner.alpha {
+                        nv12.with_alpha(frame_guard.alpha_offset(width as _, height as _))
+                    } else {
+                        nv12
+                    }))
                 })
                 .collect()
         };
@@ -643,45 +1649,270 @@ impl Video {
     }
 }
 
-fn yuv_to_rgba(
-    yuv: &[u8],
+/// Converts a sample of the text sink into its original text and a cue, or `None` if its format
+/// isn't supported. `script` caches the ASS/SSA header of the stream.
+fn parse_cue(
+    sample: &gst::Sample,
+    script: &mut Option<(String, ass::Script)>,
+) -> Result<Option<(String, SubtitleCue)>, gst::FlowError> {
+    let caps = sample.caps().ok_or(gst::FlowError::Error)?;
+    let s = caps.structure(0).ok_or(gst::FlowError::Error)?;
+    let supported = [
+        "application/x-ass",
+        "application/x-ssa",
+        "application/x-subtitle-vtt",
+        "text/x-raw",
+    ];
+    if !supported.iter().any(|name| s.has_name(name)) {
+        return Ok(None);
+    }
+
+    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+    let text = std::str::from_utf8(map.as_slice()).map_err(|_| gst::FlowError::Error)?;
+
+    let cue = if s.has_name("application/x-ass") || s.has_name("application/x-ssa") {
+        // the header with the styles comes with the caps
+        let header = s
+            .get::<gst::Buffer>("codec_data")
+            .ok()
+            .and_then(|header| {
+                let map = header.map_readable().ok()?;
+                Some(String::from_utf8_lossy(map.as_slice()).into_owned())
+            })
+            .unwrap_or_default();
+        if script.as_ref().is_none_or(|(cached, _)| *cached != header) {
+            let parsed = ass::Script::parse(&header);
+            *script = Some((header, parsed));
+        }
+        script.as_ref().unwrap().1.matroska_cue(text)
+    } else if s.has_name("application/x-subtitle-vtt") {
+        webvtt::parse_cue(text)
+    } else {
+        let markup = match s.get::<&str>("format") {
+            Ok("pango-markup") => text.to_string(),
+            _ => escape_markup(text),
+        };
+        SubtitleCue {
+            markup,
+            ..SubtitleCue::default()
+        }
+    };
+    Ok(Some((text.to_string(), cue)))
+}
+
+/// Loaded cues (ordered by start time) shown over a frame at `position` in the media lasting
+/// `duration`, with subtitles delayed by `delay`, all in nanoseconds.
+fn loaded_cues(
+    cues: &[SubtitleCue],
+    position: i64,
+    duration: u64,
+    delay: i64,
+) -> impl Iterator<Item = &SubtitleCue> {
+    // cues may outlast the range of `i64` nanoseconds
+    let nanos = |time: Duration| i64::try_from(time.as_nanos()).unwrap_or(i64::MAX);
+    let position = position.saturating_sub(delay);
+    let position_end = position.saturating_add(i64::try_from(duration).unwrap_or(i64::MAX));
+    let started = cues.partition_point(|cue| nanos(cue.start) < position_end);
+    cues[..started]
+        .iter()
+        .filter(move |cue| nanos(cue.end) > position)
+}
+
+/// Converts a sample of the text sink holding decoded image-based subtitles (e.g., from
+/// `dvdsubdec`) into a bitmap covering the frame, or `None` if it holds something else.
+fn parse_bitmap(sample: &gst::Sample) -> Result<Option<Bitmap>, gst::FlowError> {
+    let caps = sample.caps().ok_or(gst::FlowError::Error)?;
+    if !caps.structure(0).is_some_and(|s| s.has_name("video/x-raw")) {
+        return Ok(None);
+    }
+    let info = gst_video::VideoInfo::from_caps(caps).map_err(|_| gst::FlowError::NotNegotiated)?;
+    let format = info.format();
+    if ![
+        gst_video::VideoFormat::Rgba,
+        gst_video::VideoFormat::Bgra,
+        gst_video::VideoFormat::Argb,
+        gst_video::VideoFormat::Ayuv,
+    ]
+    .contains(&format)
+    {
+        log::warn!("unsupported bitmap subtitle format {format:?}");
+        return Ok(None);
+    }
+
+    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+    let (width, height) = (info.width(), info.height());
+    let stride = buffer
+        .meta::<VideoMeta>()
+        .map_or(info.stride()[0], |meta| meta.stride()[0]) as usize;
+    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for row in map.chunks(stride).take(height as usize) {
+        for pixel in row.chunks_exact(4).take(width as usize) {
+            pixels.extend(premultiplied_rgba(format, pixel));
+        }
+    }
+    if pixels.len() != (width * height * 4) as usize {
+        return Err(gst::FlowError::Error);
+    }
+
+    Ok(Some(Bitmap {
+        pixels,
+        width,
+        height,
+        rect: iced::Rectangle::new(iced::Point::ORIGIN, iced::Size::new(1.0, 1.0)),
+    }))
+}
+
+/// Converts a pixel of a packed 8-bit format with straight alpha into premultiplied RGBA.
+fn premultiplied_rgba(format: gst_video::VideoFormat, pixel: &[u8]) -> [u8; 4] {
+    let [r, g, b, a] = match format {
+        gst_video::VideoFormat::Bgra => [pixel[2], pixel[1], pixel[0], pixel[3]],
+        gst_video::VideoFormat::Argb => [pixel[1], pixel[2], pixel[3], pixel[0]],
+        gst_video::VideoFormat::Ayuv => {
+            // BT.601 limited range, as used by DVD subpictures
+            let y = 1.164 * (pixel[1] as f32 - 16.0);
+            let (u, v) = (pixel[2] as f32 - 128.0, pixel[3] as f32 - 128.0);
+            let [r, g, b] = [y + 1.596 * v, y - 0.813 * v - 0.391 * u, y + 2.018 * u]
+                .map(|channel| channel.round().clamp(0.0, 255.0) as u8);
+            [r, g, b, pixel[0]]
+        }
+        _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+    };
+    let premultiply = |channel: u8| ((channel as u32 * a as u32 + 127) / 255) as u8;
+    [premultiply(r), premultiply(g), premultiply(b), a]
+}
+
+/// Flattens the rectangles of an overlay composition on a frame of `width` by `height` pixels
+/// into a bitmap covering their bounds, or `None` if they're empty.
+fn composition_bitmap(
+    composition: &gst_video::VideoOverlayCompositionRef,
     width: u32,
     height: u32,
-    downscale: u32,
-    stride: Option<u32>,
-) -> Vec<u8> {
-    // Use stride from VideoMeta if available, otherwise assume stride == width
-    let stride = stride.unwrap_or(width);
+) -> Option<Bitmap> {
+    let rectangles: Vec<_> = composition
+        .iter()
+        .filter_map(|rectangle| {
+            let (x, y, w, h) = rectangle.render_rectangle();
+            // clip to the frame
+            let x0 = x.clamp(0, width as i32) as u32;
+            let y0 = y.clamp(0, height as i32) as u32;
+            let x1 = x.saturating_add_unsigned(w).clamp(0, width as i32) as u32;
+            let y1 = y.saturating_add_unsigned(h).clamp(0, height as i32) as u32;
+            (x1 > x0 && y1 > y0).then_some((rectangle, (x, y, w, h), [x0, y0, x1, y1]))
+        })
+        .collect();
+
+    let [min_x, min_y, max_x, max_y] = rectangles.iter().map(|(_, _, clip)| *clip).reduce(
+        |[ax0, ay0, ax1, ay1], [bx0, by0, bx1, by1]| {
+            [ax0.min(bx0), ay0.min(by0), ax1.max(bx1), ay1.max(by1)]
+        },
+    )?;
+    let (canvas_width, canvas_height) = (max_x - min_x, max_y - min_y);
+    let mut pixels = vec![0; (canvas_width * canvas_height * 4) as usize];
+
+    for (rectangle, (x, y, w, h), [x0, y0, x1, y1]) in rectangles {
+        let source =
+            rectangle.pixels_unscaled_argb(gst_video::VideoOverlayFormatFlags::PREMULTIPLIED_ALPHA);
+        let Some(meta) = source.meta::<VideoMeta>() else {
+            continue;
+        };
+        let (source_width, source_height) = (meta.width(), meta.height());
+        if source_width == 0 || source_height == 0 {
+            continue;
+        }
+        let stride = meta.stride()[0] as usize;
+        let Ok(map) = source.map_readable() else {
+            continue;
+        };
 
-    let uv_start = stride * height;
-    let mut rgba = vec![];
+        // unscaled pixels are stretched over the render rectangle
+        for target_y in y0..y1 {
+            let source_y =
+                ((target_y as i32 - y) as u32 * source_height / h).min(source_height - 1);
+            for target_x in x0..x1 {
+                let source_x =
+                    ((target_x as i32 - x) as u32 * source_width / w).min(source_width - 1);
+                let offset = source_y as usize * stride + source_x as usize * 4;
+                let Some(pixel) = map.get(offset..offset + 4) else {
+                    continue;
+                };
+                // native-endian 0xAARRGGBB
+                let [a, r, g, b] = u32::from_ne_bytes(pixel.try_into().unwrap()).to_be_bytes();
+
+                let target = (((target_y - min_y) * canvas_width + target_x - min_x) * 4) as usize;
+                let under = &mut pixels[target..target + 4];
+                // malformed data may have colors brighter than the alpha allows
+                for (channel, over) in under.iter_mut().zip([r, g, b, a]) {
+                    *channel = over
+                        .saturating_add(((*channel as u32 * (255 - a as u32) + 127) / 255) as u8);
+                }
+            }
+        }
+    }
 
-    for y in 0..height / downscale {
-        for x in 0..width / downscale {
-            let x_src = x * downscale;
-            let y_src = y * downscale;
+    Some(Bitmap {
+        pixels,
+        width: canvas_width,
+        height: canvas_height,
+        rect: iced::Rectangle {
+            x: min_x as f32 / width as f32,
+            y: min_y as f32 / height as f32,
+            width: canvas_width as f32 / width as f32,
+            height: canvas_height as f32 / height as f32,
+        },
+    })
+}
 
-            // NV12 memory layout with stride:
-            // Y plane: stride bytes per row, starting at offset 0
-            // UV plane: stride bytes per row (same stride), starting at offset stride * height
-            // Each pixel is 1 byte Y, and every 2x2 block shares 2 bytes (U, V)
-            let y_offset = (y_src * stride + x_src) as usize;
-            let uv_offset = (uv_start + (y_src / 2) * stride + (x_src / 2) * 2) as usize;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let y = yuv[y_offset] as f32;
-            let u = yuv[uv_offset] as f32;
-            let v = yuv[uv_offset + 1] as f32;
+    #[test]
+    fn loaded_cues_follow_the_delay() {
+        let cue = |start, end| SubtitleCue {
+            start: Duration::from_secs(start),
+            end: Duration::from_secs(end),
+            ..SubtitleCue::default()
+        };
+        // the last cue ends past the range of `i64` nanoseconds
+        let cues = [cue(1, 2), cue(3, 4), cue(5, u64::MAX)];
+        let shown = |position: u64, delay: Delay| {
+            loaded_cues(
+                &cues,
+                position as i64 * 1_000_000_000,
+                40_000_000,
+                delay.as_nanos(),
+            )
+            .map(|cue| cue.start.as_secs())
+            .collect::<Vec<_>>()
+        };
 
-            let r = 1.164 * (y - 16.0) + 1.596 * (v - 128.0);
-            let g = 1.164 * (y - 16.0) - 0.813 * (v - 128.0) - 0.391 * (u - 128.0);
-            let b = 1.164 * (y - 16.0) + 2.018 * (u - 128.0);
+        assert_eq!(shown(1, Delay::default()), [1]);
+        assert_eq!(shown(2, Delay::default()), [] as [u64; 0]);
+        assert_eq!(shown(1_000, Delay::default()), [5]);
+        assert_eq!(shown(2, Delay::Later(Duration::from_secs(1))), [1]);
+        assert_eq!(shown(2, Delay::Earlier(Duration::from_secs(1))), [3]);
+    }
 
-            rgba.push(r as u8);
-            rgba.push(g as u8);
-            rgba.push(b as u8);
-            rgba.push(0xFF);
+    #[test]
+    fn loaded_cues_saturate_extreme_delays() {
+        let cues = [SubtitleCue {
+            start: Duration::ZERO,
+            end: Duration::MAX,
+            ..SubtitleCue::default()
+        }];
+        for delay in [Delay::Earlier(Duration::MAX), Delay::Later(Duration::MAX)] {
+            let delay = delay.as_nanos();
+            assert!(delay == i64::MIN || delay == i64::MAX);
+            for position in [i64::MIN, -1, 0, i64::MAX] {
+                loaded_cues(&cues, position, u64::MAX, delay).for_each(drop);
+            }
         }
+        assert_eq!(
+            loaded_cues(&cues, 0, u64::MAX, Delay::Earlier(Duration::MAX).as_nanos()).count(),
+            0
+        );
     }
-
-    rgba
 }
@@ -1,44 +1,87 @@
-use crate::{pipeline::VideoPrimitive, video::Video};
+use crate::renderer::Renderer as VideoRenderer;
+use crate::{
+    effect::{Effect, EffectInstance, MAX_EFFECT_PARAMS},
+    lut::LutHandle,
+    pipeline::{Adjustments, RenderParams, VideoPrimitive},
+    style::{Catalog, Style, StyleFn},
+    subtitle::{Bitmap, Overlay, SubtitleStyle},
+    video::{AudioLevels, Frame, Internal, Video},
+    yuv,
+};
 use gstreamer as gst;
 use iced::{
     Element,
-    advanced::{self, Widget, layout, widget},
+    advanced::{self, Widget, image, layout, mouse, widget},
 };
-use iced_wgpu::primitive::Renderer as PrimitiveRenderer;
 use log::error;
 use std::{marker::PhantomData, sync::atomic::Ordering, time::Duration};
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 /// Video player widget which displays the current frame of a [`Video`](crate::Video).
 pub struct VideoPlayer<'a, Message, Theme = iced::Theme, Renderer = iced::Renderer>
 where
-    Renderer: PrimitiveRenderer,
+    Theme: Catalog,
+    Renderer: VideoRenderer,
 {
     video: &'a Video,
     content_fit: iced::ContentFit,
+    rotation: iced::Radians,
+    mirror_horizontal: bool,
+    mirror_vertical: bool,
     width: iced::Length,
     height: iced::Length,
+    zoom: f32,
+    pan: iced::Vector,
+    interactive_zoom: bool,
+    adjustments: Adjustments,
+    effects: Vec<EffectInstance>,
+    lut: Option<LutHandle>,
+    lut_strength: f32,
+    filter_mode: FilterMode,
+    class: Theme::Class<'a>,
+    show_subtitles: bool,
+    subtitle_style: SubtitleStyle,
     on_end_of_stream: Option<Message>,
     on_new_frame: Option<Message>,
     on_subtitle_text: Option<Box<dyn Fn(Option<String>) -> Message + 'a>>,
+    on_audio_levels: Option<Box<dyn Fn(AudioLevels) -> Message + 'a>>,
     on_error: Option<Box<dyn Fn(&glib::Error) -> Message + 'a>>,
     _phantom: PhantomData<(Theme, Renderer)>,
 }
 
 impl<'a, Message, Theme, Renderer> VideoPlayer<'a, Message, Theme, Renderer>
 where
-    Renderer: PrimitiveRenderer,
+    Theme: Catalog,
+    Renderer: VideoRenderer,
 {
     /// Creates a new video player widget for a given video.
     pub fn new(video: &'a Video) -> Self {
         VideoPlayer {
             video,
             content_fit: iced::ContentFit::default(),
+            rotation: iced::Radians(0.0),
+            mirror_horizontal: false,
+            mirror_vertical: false,
             width: iced::Length::Shrink,
             height: iced::Length::Shrink,
+            zoom: 1.0,
+            pan: iced::Vector::ZERO,
+            interactive_zoom: false,
+            adjustments: Adjustments::default(),
+            effects: Vec::new(),
+            lut: None,
+            lut_strength: 1.0,
+            filter_mode: FilterMode::default(),
+            class: Theme::default(),
+            show_subtitles: false,
+            subtitle_style: SubtitleStyle::default(),
             on_end_of_stream: None,
             on_new_frame: None,
             on_subtitle_text: None,
+            on_audio_levels: None,
             on_error: None,
             _phantom: Default::default(),
         }
@@ -68,6 +111,156 @@ where
         }
     }
 
+    /// Rotates the video clockwise around its center. The layout fits the rotated video, so
+    /// `90` turns a landscape video into a portrait one.
+    pub fn rotation(self, rotation: impl Into<iced::Degrees>) -> Self {
+        VideoPlayer {
+            rotation: rotation.into().into(),
+            ..self
+        }
+    }
+
+    /// Mirrors the video left to right (e.g., for webcam previews).
+    ///
+    /// Not applied when a renderer without primitive support falls back to CPU rendering.
+    pub fn mirror_horizontal(self, mirror_horizontal: bool) -> Self {
+        VideoPlayer {
+            mirror_horizontal,
+            ..self
+        }
+    }
+
+    /// Mirrors the video top to bottom.
+    ///
+    /// Not applied when a renderer without primitive support falls back to CPU rendering.
+    pub fn mirror_vertical(self, mirror_vertical: bool) -> Self {
+        VideoPlayer {
+            mirror_vertical,
+            ..self
+        }
+    }
+
+    /// Sets the zoom factor of the `VideoPlayer`.
+    /// `1.0` shows the whole video, `2.0` shows half of its width and height, and so on.
+    /// Values are clamped to `1.0..=32.0`.
+    pub fn zoom(self, zoom: f32) -> Self {
+        VideoPlayer { zoom, ..self }
+    }
+
+    /// Sets the center of the visible region when zoomed in, as an offset from the center of the video
+    /// in fractions of its size. For example, `Vector::new(0.25, 0.0)` centers the view on the right half.
+    ///
+    /// The visible region is kept within the video.
+    pub fn pan(self, pan: iced::Vector) -> Self {
+        VideoPlayer { pan, ..self }
+    }
+
+    /// Enables zooming with the mouse wheel and panning by dragging with the left mouse button.
+    /// This is applied on top of [`zoom`](Self::zoom) and [`pan`](Self::pan).
+    pub fn interactive_zoom(self, interactive_zoom: bool) -> Self {
+        VideoPlayer {
+            interactive_zoom,
+            ..self
+        }
+    }
+
+    /// Sets how the video is sampled when it's scaled. The default is [`FilterMode::Bilinear`].
+    pub fn filter_mode(self, filter_mode: FilterMode) -> Self {
+        VideoPlayer {
+            filter_mode,
+            ..self
+        }
+    }
+
+    /// Offsets the brightness of the video.
+    /// `0.0` leaves it unchanged, `-1.0` makes it black and `1.0` makes it white.
+    pub fn brightness(self, brightness: f32) -> Self {
+        VideoPlayer {
+            adjustments: Adjustments {
+                brightness,
+                ..self.adjustments
+            },
+            ..self
+        }
+    }
+
+    /// Scales the contrast of the video around mid-gray.
+    /// `1.0` leaves it unchanged and `0.0` makes it flat gray.
+    pub fn contrast(self, contrast: f32) -> Self {
+        VideoPlayer {
+            adjustments: Adjustments {
+                contrast,
+                ..self.adjustments
+            },
+            ..self
+        }
+    }
+
+    /// Scales the saturation of the video.
+    /// `1.0` leaves it unchanged, `0.0` makes it grayscale and values above `1.0` make it more vivid.
+    pub fn saturation(self, saturation: f32) -> Self {
+        VideoPlayer {
+            adjustments: Adjustments {
+                saturation,
+                ..self.adjustments
+            },
+            ..self
+        }
+    }
+
+    /// Rotates the hue of the video.
+    pub fn hue(self, hue: impl Into<iced::Radians>) -> Self {
+        VideoPlayer {
+            adjustments: Adjustments {
+                hue: hue.into().0,
+                ..self.adjustments
+            },
+            ..self
+        }
+    }
+
+    /// Applies gamma correction to the video.
+    /// `1.0` leaves it unchanged, values above `1.0` brighten the midtones and values below darken them.
+    pub fn gamma(self, gamma: f32) -> Self {
+        VideoPlayer {
+            adjustments: Adjustments {
+                gamma,
+                ..self.adjustments
+            },
+            ..self
+        }
+    }
+
+    /// Sets a 3D LUT to color grade the video with, applied after picture adjustments.
+    pub fn lut(self, lut: Option<LutHandle>) -> Self {
+        VideoPlayer { lut, ..self }
+    }
+
+    /// Sets how much of the LUT is applied, blending between the original colors at `0.0` and the graded ones at `1.0`.
+    /// The default is `1.0`.
+    pub fn lut_strength(self, lut_strength: f32) -> Self {
+        VideoPlayer {
+            lut_strength,
+            ..self
+        }
+    }
+
+    /// Appends a post-processing [`Effect`] to the chain applied to the video, after picture adjustments.
+    ///
+    /// Up to [`MAX_EFFECT_PARAMS`] `params` are passed to the effect; missing ones are zero.
+    pub fn effect(mut self, effect: &Effect, params: &[f32]) -> Self {
+        let mut padded = [0.0; MAX_EFFECT_PARAMS];
+        for (param, value) in padded.iter_mut().zip(params) {
+            *param = *value;
+        }
+
+        self.effects.push(EffectInstance {
+            effect: effect.clone(),
+            params: padded,
+        });
+        self
+    }
+
     /// Message to send when the video reaches the end of stream (i.e., the video ends).
     pub fn on_end_of_stream(self, on_end_of_stream: Message) -> Self {
         VideoPlayer {
@@ -84,7 +277,8 @@ where
         }
     }
 
-    /// Message to send when the video receives a new frame.
+    /// Message to send when the subtitle text changes, with the text of each cue as delivered by
+    /// GStreamer, one per line. Loaded subtitles and closed captions are given without markup.
     pub fn on_subtitle_text<F>(self, on_subtitle_text: F) -> Self
     where
         F: 'a + Fn(Option<String>) -> Message,
@@ -95,6 +289,56 @@ where
         }
     }
 
+    /// Message to send when new audio levels are measured, as enabled by
+    /// [`Video::set_audio_metering`] and [`Video::set_audio_spectrum`].
+    pub fn on_audio_levels<F>(self, on_audio_levels: F) -> Self
+    where
+        F: 'a + Fn(AudioLevels) -> Message,
+    {
+        VideoPlayer {
+            on_audio_levels: Some(Box::new(on_audio_levels)),
+            ..self
+        }
+    }
+
+    /// Sets the style of the `VideoPlayer`.
+    pub fn style(self, style: impl Fn(&Theme) -> Style + 'a) -> Self
+    where
+        Theme::Class<'a>: From<StyleFn<'a, Theme>>,
+    {
+        VideoPlayer {
+            class: (Box::new(style) as StyleFn<'a, Theme>).into(),
+            ..self
+        }
+    }
+
+    /// Sets the style class of the `VideoPlayer`.
+    pub fn class(self, class: impl Into<Theme::Class<'a>>) -> Self {
+        VideoPlayer {
+            class: class.into(),
+            ..self
+        }
+    }
+
+    /// Draws the current subtitles over the bottom of the video, keeping the bold, italic and
+    /// color markup delivered by GStreamer. Cues positioned by the subtitle format (e.g., ASS/SSA
+    /// or WebVTT) are drawn where the format places them, and image-based subtitles (e.g., PGS,
+    /// VobSub or DVB) are composited over the video.
+    pub fn show_subtitles(self, show_subtitles: bool) -> Self {
+        VideoPlayer {
+            show_subtitles,
+            ..self
+        }
+    }
+
+    /// Sets the appearance of subtitles drawn with [`show_subtitles`](Self::show_subtitles).
+    pub fn subtitle_style(self, subtitle_style: SubtitleStyle) -> Self {
+        VideoPlayer {
+            subtitle_style,
+            ..self
+        }
+    }
+
     /// Message to send when the video playback encounters an error.
     pub fn on_error<F>(self, on_error: F) -> Self
     where
@@ -105,14 +349,253 @@ where
             ..self
         }
     }
+
+    /// Computes where the video is drawn within the widget `bounds`, which contains the whole video
+    /// when rotated.
+    pub(crate) fn drawing_bounds(
+        &self,
+        inner: &Internal,
+        bounds: iced::Rectangle,
+    ) -> iced::Rectangle {
+        // based on `Image::draw`
+        let image_size = inner.display_size().rotate(self.rotation);
+        let adjusted_fit = self.content_fit.fit(image_size, bounds.size());
+        let scale = iced::Vector::new(
+            adjusted_fit.width / image_size.width,
+            adjusted_fit.height / image_size.height,
+        );
+        let final_size = image_size * scale;
+
+        let position = match self.content_fit {
+            iced::ContentFit::None => iced::Point::new(
+                bounds.x + (image_size.width - adjusted_fit.width) / 2.0,
+                bounds.y + (image_size.height - adjusted_fit.height) / 2.0,
+            ),
+            _ => iced::Point::new(
+                bounds.center_x() - final_size.width / 2.0,
+                bounds.center_y() - final_size.height / 2.0,
+            ),
+        };
+
+        iced::Rectangle::new(position, final_size)
+    }
+
+    /// Computes the bounds of the video before rotation and mirroring, centered within `drawing_bounds`.
+    pub(crate) fn video_bounds(
+        &self,
+        inner: &Internal,
+        drawing_bounds: iced::Rectangle,
+    ) -> iced::Rectangle {
+        let image_size = inner.display_size();
+        let scale = drawing_bounds.width / image_size.rotate(self.rotation).width;
+        let size = image_size * scale;
+
+        iced::Rectangle::new(
+            drawing_bounds.center() - iced::Vector::new(size.width / 2.0, size.height / 2.0),
+            size,
+        )
+    }
+
+    /// Maps the cursor onto the video before rotation and mirroring, if it's over the video.
+    fn video_position(
+        &self,
+        position: iced::Point,
+        video_bounds: iced::Rectangle,
+    ) -> Option<iced::Point> {
+        let center = video_bounds.center();
+        let position = center + self.untransform(position - center);
+        video_bounds.contains(position).then_some(position)
+    }
+
+    /// Undoes the rotation and mirroring of a vector on screen.
+    fn untransform(&self, vector: iced::Vector) -> iced::Vector {
+        let vector = rotate(vector, -self.rotation.0);
+        iced::Vector::new(
+            if self.mirror_horizontal {
+                -vector.x
+            } else {
+                vector.x
+            },
+            if self.mirror_vertical {
+                -vector.y
+            } else {
+                vector.y
+            },
+        )
+    }
+
+    /// The zoom factor set with [`zoom`](Self::zoom), within its documented range.
+    fn base_zoom(&self) -> f32 {
+        self.zoom.clamp(1.0, MAX_ZOOM)
+    }
+
+    /// Computes the zoomed-in region of the (cropped) video, in coordinates normalized to its size.
+    pub(crate) fn uv_rect<P>(&self, state: &State<P>) -> iced::Rectangle {
+        let size = 1.0 / (self.base_zoom() * state.zoom).max(1.0);
+        let half = size / 2.0;
+        let center = iced::Point::new(
+            (0.5 + self.pan.x + state.pan.x).clamp(half, 1.0 - half),
+            (0.5 + self.pan.y + state.pan.y).clamp(half, 1.0 - half),
+        );
+
+        iced::Rectangle::new(
+            center - iced::Vector::new(half, half),
+            iced::Size::new(size, size),
+        )
+    }
+
+    /// Computes the visible region of the frame in normalized texture coordinates,
+    /// applying zoom and pan within the crop region.
+    pub(crate) fn visible_uv<P>(&self, inner: &Internal, state: &State<P>) -> iced::Rectangle {
+        let crop = inner.crop_uv();
+        let uv = self.uv_rect(state);
+        iced::Rectangle {
+            x: crop.x + uv.x * crop.width,
+            y: crop.y + uv.y * crop.height,
+            width: uv.width * crop.width,
+            height: uv.height * crop.height,
+        }
+    }
+
+    /// Creates the primitive which draws the current frame.
+    pub(crate) fn primitive<P>(
+        &self,
+        inner: &Internal,
+        state: &State<P>,
+        style: &Style,
+        drawing_bounds: iced::Rectangle,
+        frame: iced::Rectangle,
+        upload_frame: bool,
+        upload_subtitle: bool,
+    ) -> VideoPrimitive {
+        VideoPrimitive::new(
+            inner.id,
+            Arc::clone(&inner.alive),
+            Arc::clone(&inner.frame),
+            (inner.width as _, inner.height as _),
+            inner.alpha,
+            RenderParams {
+                uv_rect: self.visible_uv(inner, state),
+                adjustments: self.adjustments,
+                effects: self.effects.clone(),
+                lut: self.lut.clone(),
+                lut_strength: self.lut_strength,
+                filter_mode: self.filter_mode,
+                quad: self.video_bounds(inner, drawing_bounds),
+                rotation: self.rotation,
+                mirror_horizontal: self.mirror_horizontal,
+                mirror_vertical: self.mirror_vertical,
+                frame,
+                border: style.border,
+                opacity: style.opacity,
+                subtitles: self.show_subtitles,
+            },
+            upload_frame,
+            upload_subtitle.then(|| Arc::clone(&inner.subtitle_bitmap)),
+        )
+    }
+
+    /// Applies mouse wheel zoom and drag panning, returning whether `state` changed.
+    fn update_zoom<P>(
+        &self,
+        state: &mut State<P>,
+        event: &mouse::Event,
+        cursor: mouse::Cursor,
+        video_bounds: iced::Rectangle,
+    ) -> bool {
+        match *event {
+            mouse::Event::WheelScrolled { delta } => {
+                let Some(position) = cursor
+                    .position()
+                    .and_then(|position| self.video_position(position, video_bounds))
+                else {
+                    return false;
+                };
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / 60.0,
+                };
+
+                // keep the point under the cursor fixed while zooming
+                let before = self.uv_rect(state);
+                let relative = iced::Vector::new(
+                    (position.x - video_bounds.x) / video_bounds.width,
+                    (position.y - video_bounds.y) / video_bounds.height,
+                );
+                let anchor = iced::Point::new(
+                    before.x + relative.x * before.width,
+                    before.y + relative.y * before.height,
+                );
+
+                let zoom =
+                    (self.base_zoom() * state.zoom * 1.1f32.powf(lines)).clamp(1.0, MAX_ZOOM);
+                state.zoom = zoom / self.base_zoom();
+
+                let size = 1.0 / zoom;
+                state.pan = iced::Vector::new(
+                    anchor.x + (0.5 - relative.x) * size - 0.5 - self.pan.x,
+                    anchor.y + (0.5 - relative.y) * size - 0.5 - self.pan.y,
+                );
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                let Some(position) = cursor
+                    .position()
+                    .filter(|position| self.video_position(*position, video_bounds).is_some())
+                else {
+                    return false;
+                };
+                state.drag_origin = Some(position);
+                return true;
+            }
+            mouse::Event::CursorMoved { position } => {
+                let Some(origin) = state.drag_origin else {
+                    return false;
+                };
+                let visible = self.uv_rect(state);
+                let delta = self.untransform(position - origin);
+                state.pan.x -= delta.x / video_bounds.width * visible.width;
+                state.pan.y -= delta.y / video_bounds.height * visible.height;
+                state.drag_origin = Some(position);
+            }
+            mouse::Event::ButtonReleased(mouse::Button::Left) => {
+                return state.drag_origin.take().is_some();
+            }
+            _ => return false,
+        }
+
+        // drop any pan that the visible region was clamped out of, so panning back responds immediately
+        let visible = self.uv_rect(state);
+        state.pan = visible.center() - iced::Point::new(0.5, 0.5) - self.pan;
+
+        true
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn video(&self) -> &'a Video {
+        self.video
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn style_class(&self) -> &Theme::Class<'a> {
+        &self.class
+    }
 }
 
 impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer>
     for VideoPlayer<'_, Message, Theme, Renderer>
 where
     Message: Clone,
-    Renderer: PrimitiveRenderer,
+    Theme: Catalog,
+    Renderer: VideoRenderer,
 {
+    fn tag(&self) -> widget::tree::Tag {
+        widget::tree::Tag::of::<State<Renderer::Paragraph>>()
+    }
+
+    fn state(&self) -> widget::tree::State {
+        widget::tree::State::new(State::<Renderer::Paragraph>::default())
+    }
+
     fn size(&self) -> iced::Size<iced::Length> {
         iced::Size {
             width: iced::Length::Shrink,
@@ -126,10 +609,8 @@ where
         _renderer: &Renderer,
         limits: &layout::Limits,
     ) -> layout::Node {
-        let (video_width, video_height) = self.video.size();
-
         // based on `Image::layout`
-        let image_size = iced::Size::new(video_width as f32, video_height as f32);
+        let image_size = self.video.read().display_size().rotate(self.rotation);
         let raw_size = limits.resolve(self.width, self.height, image_size);
         let full_size = self.content_fit.fit(image_size, raw_size);
         let final_size = iced::Size {
@@ -148,9 +629,9 @@ where
 
     fn draw(
         &self,
-        _tree: &widget::Tree,
+        tree: &widget::Tree,
         renderer: &mut Renderer,
-        _theme: &Theme,
+        theme: &Theme,
         _style: &advanced::renderer::Style,
         layout: advanced::Layout<'_>,
         _cursor: advanced::mouse::Cursor,
@@ -158,30 +639,29 @@ where
     ) {
         let mut inner = self.video.write();
 
-        // bounds based on `Image::draw`
-        let image_size = iced::Size::new(inner.width as f32, inner.height as f32);
+        let state = tree.state.downcast_ref::<State<Renderer::Paragraph>>();
         let bounds = layout.bounds();
-        let adjusted_fit = self.content_fit.fit(image_size, bounds.size());
-        let scale = iced::Vector::new(
-            adjusted_fit.width / image_size.width,
-            adjusted_fit.height / image_size.height,
-        );
-        let final_size = image_size * scale;
+        let drawing_bounds = self.drawing_bounds(&inner, bounds);
+        let style = theme.style(&self.class);
+        // the visible part of the video, which is what gets rounded and bordered
+        let frame = drawing_bounds.intersection(&bounds).unwrap_or(bounds);
 
-        let position = match self.content_fit {
-            iced::ContentFit::None => iced::Point::new(
-                bounds.x + (image_size.width - adjusted_fit.width) / 2.0,
-                bounds.y + (image_size.height - adjusted_fit.height) / 2.0,
-            ),
-            _ => iced::Point::new(
-                bounds.center_x() - final_size.width / 2.0,
-                bounds.center_y() - final_size.height / 2.0,
-            ),
-        };
-
-        let drawing_bounds = iced::Rectangle::new(position, final_size);
+        if let Some(background) = style.background {
+            renderer.fill_quad(
+                advanced::renderer::Quad {
+                    bounds,
+                    border: iced::Border {
+                        radius: style.border.radius,
+                        ..iced::Border::default()
+                    },
+                    ..advanced::renderer::Quad::default()
+                },
+                background.scale_alpha(style.opacity),
+            );
+        }
 
         let upload_frame = inner.upload_frame.swap(false, Ordering::SeqCst);
+        let upload_subtitle = inner.upload_bitmap.swap(false, Ordering::SeqCst);
 
         if upload_frame {
             let last_frame_time = inner
@@ -192,32 +672,91 @@ where
             inner.set_av_offset(Instant::now() - last_frame_time);
         }
 
+        let primitive = self.primitive(
+            &inner,
+            state,
+            &style,
+            drawing_bounds,
+            frame,
+            upload_frame,
+            upload_subtitle,
+        );
+        let video_bounds = self.video_bounds(&inner, drawing_bounds);
+        let uv = self.visible_uv(&inner, state);
+        let cpu_frame = CpuFrame {
+            cache: Arc::clone(&inner.cpu_frame),
+            frame: Arc::clone(&inner.frame),
+            bitmap: self
+                .show_subtitles
+                .then(|| Arc::clone(&inner.subtitle_bitmap)),
+            width: inner.width as u32,
+            height: inner.height as u32,
+            alpha: inner.alpha,
+            changed: upload_frame || upload_subtitle,
+        };
+        // converting on the CPU is slow, so it mustn't hold up other users of the video
+        drop(inner);
+
         let render = |renderer: &mut Renderer| {
-            renderer.draw_primitive(
-                drawing_bounds,
-                VideoPrimitive::new(
-                    inner.id,
-                    Arc::clone(&inner.alive),
-                    Arc::clone(&inner.frame),
-                    (inner.width as _, inner.height as _),
-                    upload_frame,
-                ),
-            );
+            // no primitive support (e.g., tiny-skia), so convert on the CPU and draw as an image instead
+            if !renderer.draw_video_primitive(drawing_bounds, primitive) {
+                if let Some(handle) = cpu_frame.handle() {
+                    // draw the whole frame scaled up so the visible region covers the video bounds
+                    let mut image_bounds = iced::Rectangle {
+                        x: video_bounds.x - uv.x / uv.width * video_bounds.width,
+                        y: video_bounds.y - uv.y / uv.height * video_bounds.height,
+                        width: video_bounds.width / uv.width,
+                        height: video_bounds.height / uv.height,
+                    };
+                    // images rotate around their own center rather than the center of the video
+                    let offset = image_bounds.center() - video_bounds.center();
+                    let shift = rotate(offset, self.rotation.0) - offset;
+                    image_bounds.x += shift.x;
+                    image_bounds.y += shift.y;
+                    let mut image = image::Image::new(handle)
+                        .filter_method(match self.filter_mode {
+                            FilterMode::Nearest => image::FilterMethod::Nearest,
+                            _ => image::FilterMethod::Linear,
+                        })
+                        .rotation(self.rotation)
+                        .opacity(style.opacity);
+                    image.border_radius = style.border.radius;
+                    renderer.draw_image(image, image_bounds, frame);
+                }
+                if style.border.width > 0.0 {
+                    renderer.fill_quad(
+                        advanced::renderer::Quad {
+                            bounds: frame,
+                            border: iced::Border {
+                                color: style.border.color.scale_alpha(style.opacity),
+                                ..style.border
+                            },
+                            ..advanced::renderer::Quad::default()
+                        },
+                        iced::Color::TRANSPARENT,
+                    );
+                }
+            }
         };
 
-        if adjusted_fit.width > bounds.width || adjusted_fit.height > bounds.height {
+        if drawing_bounds.width > bounds.width || drawing_bounds.height > bounds.height {
             renderer.with_layer(bounds, render);
         } else {
             render(renderer);
         }
+
+        if self.show_subtitles {
+            // a separate layer, so the text is drawn above the video
+            renderer.with_layer(frame, |renderer| state.subtitle.draw(renderer, frame));
+        }
     }
 
     fn update(
         &mut self,
-        _tree: &mut widget::Tree,
+        tree: &mut widget::Tree,
         event: &iced::Event,
-        _layout: advanced::Layout<'_>,
-        _cursor: advanced::mouse::Cursor,
+        layout: advanced::Layout<'_>,
+        cursor: advanced::mouse::Cursor,
         _renderer: &Renderer,
         _clipboard: &mut dyn advanced::Clipboard,
         shell: &mut advanced::Shell<'_, Message>,
@@ -226,6 +765,20 @@ where
         let mut inner = self.video.write();
 
         if let iced::Event::Window(iced::window::Event::RedrawRequested(_)) = event {
+            if self.show_subtitles {
+                let bounds = layout.bounds();
+                let frame = self
+                    .drawing_bounds(&inner, bounds)
+                    .intersection(&bounds)
+                    .unwrap_or(bounds);
+                let state = tree.state.downcast_mut::<State<Renderer::Paragraph>>();
+                if let Ok(cues) = inner.subtitle_cues.try_lock() {
+                    state
+                        .subtitle
+                        .update(&cues, &self.subtitle_style, frame.size());
+                }
+            }
+
             if inner.restart_stream || (!inner.is_eos && !inner.paused()) {
                 let mut restart_stream = false;
                 let emit_eos = !inner.restart_stream;
@@ -235,11 +788,13 @@ where
                     inner.restart_stream = false;
                 }
                 let mut eos_pause = false;
+                let mut audio_levels = false;
 
-                while let Some(msg) = inner
-                    .bus
-                    .pop_filtered(&[gst::MessageType::Error, gst::MessageType::Eos])
-                {
+                while let Some(msg) = inner.bus.pop_filtered(&[
+                    gst::MessageType::Error,
+                    gst::MessageType::Eos,
+                    gst::MessageType::Element,
+                ]) {
                     match msg.view() {
                         gst::MessageView::Error(err) => {
                             error!("bus returned an error: {err}");
@@ -259,10 +814,22 @@ where
                                 eos_pause = true;
                             }
                         }
+                        gst::MessageView::Element(element) => {
+                            if let Some(structure) = element.structure() {
+                                audio_levels |= inner.update_audio_levels(structure);
+                            }
+                        }
                         _ => {}
                     }
                 }
 
+                if audio_levels
+                    && let Some(on_audio_levels) = &self.on_audio_levels
+                    && let Some(levels) = &inner.audio_levels
+                {
+                    shell.publish(on_audio_levels(levels.clone()));
+                }
+
                 // Don't run eos_pause if restart_stream is true; fixes "pausing" after restarting a stream
                 if restart_stream {
                     if let Err(err) = inner.restart_stream() {
@@ -293,16 +860,160 @@ where
                     Instant::now() + Duration::from_millis(32),
                 ));
             }
+        } else if let iced::Event::Mouse(event) = event
+            && self.interactive_zoom
+        {
+            let drawing_bounds = self.drawing_bounds(&inner, layout.bounds());
+            let video_bounds = self.video_bounds(&inner, drawing_bounds);
+            let state = tree.state.downcast_mut::<State<Renderer::Paragraph>>();
+            if self.update_zoom(state, event, cursor, video_bounds) {
+                shell.capture_event();
+                shell.request_redraw();
+            }
         }
     }
+
+    fn mouse_interaction(
+        &self,
+        tree: &widget::Tree,
+        layout: advanced::Layout<'_>,
+        cursor: advanced::mouse::Cursor,
+        _viewport: &iced::Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if !self.interactive_zoom {
+            return mouse::Interaction::None;
+        }
+
+        if tree
+            .state
+            .downcast_ref::<State<Renderer::Paragraph>>()
+            .drag_origin
+            .is_some()
+        {
+            mouse::Interaction::Grabbing
+        } else if cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Grab
+        } else {
+            mouse::Interaction::None
+        }
+    }
+}
+
+/// How the video is sampled when it's drawn at a different size than its resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FilterMode {
+    /// Picks the closest pixel, keeping hard edges (e.g., for pixel art or inspecting individual pixels).
+    Nearest,
+    /// Interpolates between the four closest pixels. Cheap, but aliases when downscaling heavily.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom bicubic interpolation, which is sharper than bilinear when upscaling.
+    Bicubic,
+    /// Lanczos interpolation with 3 lobes, which is the sharpest when upscaling, at the highest cost.
+    Lanczos,
+    /// Trilinear interpolation between generated mip levels, which avoids aliasing when downscaling heavily
+    /// (e.g., a large video in a small thumbnail).
+    Mipmapped,
+}
+
+/// Largest zoom factor reachable with the mouse wheel.
+const MAX_ZOOM: f32 = 32.0;
+
+/// Zoom and pan applied through mouse interaction, on top of the values set on the widget,
+/// and the laid out subtitles.
+#[derive(Debug)]
+pub(crate) struct State<P = ()> {
+    zoom: f32,
+    pan: iced::Vector,
+    drag_origin: Option<iced::Point>,
+    subtitle: Overlay<P>,
+}
+
+impl<P: Default> Default for State<P> {
+    fn default() -> Self {
+        State {
+            zoom: 1.0,
+            pan: iced::Vector::ZERO,
+            drag_origin: None,
+            subtitle: Overlay::default(),
+        }
+    }
+}
+
+/// Rotates `vector` clockwise on screen by `radians`.
+fn rotate(vector: iced::Vector, radians: f32) -> iced::Vector {
+    let (sin, cos) = radians.sin_cos();
+    iced::Vector::new(
+        vector.x * cos - vector.y * sin,
+        vector.x * sin + vector.y * cos,
+    )
+}
+
+/// The current frame of a video, converted to RGBA on the CPU for renderers without primitive
+/// support, without locking the video.
+struct CpuFrame {
+    /// Last frame converted, and whether subtitles were composited onto it.
+    cache: Arc<Mutex<Option<(image::Handle, bool)>>>,
+    frame: Arc<Mutex<Frame>>,
+    /// Bitmap subtitles to composite, if they're shown.
+    bitmap: Option<Arc<Mutex<Option<Bitmap>>>>,
+    width: u32,
+    height: u32,
+    alpha: bool,
+    /// A frame or bitmap arrived since the last one was drawn.
+    changed: bool,
+}
+
+impl CpuFrame {
+    /// Returns the last frame converted, converting the current one if anything changed since.
+    fn handle(self) -> Option<image::Handle> {
+        let subtitles = self.bitmap.is_some();
+        let mut cache = self.cache.lock().ok()?;
+        if let Some((handle, cached_subtitles)) = cache.as_ref()
+            && !self.changed
+            && *cached_subtitles == subtitles
+        {
+            return Some(handle.clone());
+        }
+
+        // the sample is shared, so the worker is free to replace the frame during the conversion
+        let frame = self.frame.lock().ok()?.clone();
+        let bitmap = match &self.bitmap {
+            Some(bitmap) => bitmap.lock().ok()?.clone(),
+            None => None,
+        };
+
+        let (width, height) = (self.width, self.height);
+        let map = frame.readable()?;
+        let mut nv12 = yuv::Nv12::new(map.as_slice(), width, height, frame.stride());
+        if self.alpha {
+            nv12 = nv12.with_alpha(frame.alpha_offset(width, height));
+        }
+        // BT.709, matching the shader
+        let mut pixels = yuv::nv12_to_rgba(
+            &nv12,
+            width,
+            height,
+            yuv::Filter::Nearest,
+            yuv::Matrix::Bt709,
+        );
+        if let Some(bitmap) = bitmap {
+            bitmap.composite(&mut pixels, width, height);
+        }
+
+        let handle = image::Handle::from_rgba(width, height, pixels);
+        *cache = Some((handle.clone(), subtitles));
+        Some(handle)
+    }
 }
 
 impl<'a, Message, Theme, Renderer> From<VideoPlayer<'a, Message, Theme, Renderer>>
     for Element<'a, Message, Theme, Renderer>
 where
     Message: 'a + Clone,
-    Theme: 'a,
-    Renderer: 'a + PrimitiveRenderer,
+    Theme: 'a + Catalog,
+    Renderer: 'a + VideoRenderer,
 {
     fn from(video_player: VideoPlayer<'a, Message, Theme, Renderer>) -> Self {
         Self::new(video_player)
@@ -0,0 +1,258 @@
+//! Min/max peaks of the audio track, decoded on a pipeline of its own for waveform overviews.
+
+use crate::Error;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+use gstreamer_app::prelude::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Resolution at which peaks are decoded and cached, which waveforms of any width are grouped from.
+const PEAKS_PER_SECOND: usize = 100;
+
+/// How long to wait for a sample before checking for errors and cancellation again.
+const PULL_TIMEOUT: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+
+type Peaks = Arc<Vec<(f32, f32)>>;
+
+/// Decoding of the peaks of a whole track, shared by every waveform of the video.
+#[derive(Debug, Default)]
+pub(crate) struct Decode {
+    state: Mutex<State>,
+    cancelled: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Peaks once the track is decoded, or the error which stopped it.
+    peaks: Option<Result<Peaks, String>>,
+    wakers: Vec<Waker>,
+}
+
+impl Decode {
+    /// Starts decoding the audio track of `uri` on a thread of its own.
+    pub fn start(uri: String) -> Arc<Self> {
+        let decode = Arc::new(Decode::default());
+        let decode_ref = Arc::clone(&decode);
+        std::thread::spawn(move || {
+            let peaks = decode_peaks(&uri, &decode_ref.cancelled)
+                .map(Arc::new)
+                .map_err(|err| err.to_string());
+            let mut state = decode_ref.state.lock().expect("lock waveform");
+            state.peaks = Some(peaks);
+            state.wakers.drain(..).for_each(Waker::wake);
+        });
+        decode
+    }
+
+    /// Whether decoding stopped with an error, so it's worth starting over.
+    pub fn failed(&self) -> bool {
+        matches!(
+            self.state.lock().expect("lock waveform").peaks,
+            Some(Err(_))
+        )
+    }
+
+    /// Stops decoding, e.g., once the video is dropped. Waiting for the peaks fails.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits for the peaks of the whole track.
+    pub fn peaks(self: Arc<Self>) -> impl Future<Output = Result<Peaks, Error>> {
+        Wait(self)
+    }
+}
+
+struct Wait(Arc<Decode>);
+
+impl Future for Wait {
+    type Output = Result<Peaks, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.state.lock().expect("lock waveform");
+        match &state.peaks {
+            Some(peaks) => Poll::Ready(peaks.clone().map_err(Error::Waveform)),
+            None => {
+                if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    state.wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Groups the peaks into `buckets` equal slices of the track, each at least one peak long.
+pub(crate) fn buckets(peaks: &[(f32, f32)], buckets: usize) -> Vec<(f32, f32)> {
+    if peaks.is_empty() {
+        return vec![(0.0, 0.0); buckets];
+    }
+    (0..buckets)
+        .map(|bucket| {
+            let start = (bucket * peaks.len() / buckets).min(peaks.len() - 1);
+            let end = ((bucket + 1) * peaks.len() / buckets).max(start + 1);
+            peaks[start..end]
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(min, max), peak| {
+                    (min.min(peak.0), max.max(peak.1))
+                })
+        })
+        .collect()
+}
+
+/// Decodes the audio track as fast as possible, taking the lowest and highest sample of all
+/// channels over each block, until it ends, fails or `cancelled` is set.
+fn decode_peaks(uri: &str, cancelled: &AtomicBool) -> Result<Vec<(f32, f32)>, Error> {
+    // the video and subtitles aren't decoded at all
+    let pipeline = format!(
+        "playbin uri=\"{uri}\" flags=audio audio-sink=\"audioconvert ! appsink name=iced_waveform sync=false caps=audio/x-raw,format=F32LE,layout=interleaved\""
+    );
+    let pipeline = gst::parse::launch(pipeline.as_ref())?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| Error::Cast)?;
+
+    let audio_sink: gst::Element = pipeline.property("audio-sink");
+    let audio_sink = audio_sink
+        .downcast::<gst::Bin>()
+        .map_err(|_| Error::Cast)?
+        .by_name("iced_waveform")
+        .ok_or_else(|| Error::AppSink("iced_waveform".to_string()))?
+        .downcast::<gst_app::AppSink>()
+        .map_err(|_| Error::Cast)?;
+
+    let peaks = (|| {
+        let bus = pipeline.bus().ok_or(Error::Bus)?;
+        pipeline.set_state(gst::State::Playing)?;
+
+        let mut peaks = Vec::new();
+        let (mut min, mut max, mut frames) = (f32::MAX, f32::MIN, 0);
+        loop {
+            if cancelled.load(Ordering::SeqCst) {
+                return Err(Error::Waveform("decoding was cancelled".to_string()));
+            }
+            // errors don't end the stream, so they'd leave pulling waiting forever
+            if let Some(msg) = bus.pop_filtered(&[gst::MessageType::Error])
+                && let gst::MessageView::Error(err) = msg.view()
+            {
+                return Err(err.error().into());
+            }
+            let Some(sample) = audio_sink.try_pull_sample(PULL_TIMEOUT) else {
+                if audio_sink.is_eos() {
+                    break;
+                }
+                continue;
+            };
+
+            let caps = sample.caps().ok_or(Error::Caps)?;
+            let s = caps.structure(0).ok_or(Error::Caps)?;
+            let rate = s.get::<i32>("rate").map_err(|_| Error::Caps)?;
+            let channels = s.get::<i32>("channels").map_err(|_| Error::Caps)?;
+            let block = (rate as usize / PEAKS_PER_SECOND).max(1);
+
+            let Some(buffer) = sample.buffer() else {
+                continue;
+            };
+            let map = buffer.map_readable()?;
+            for frame in map.as_slice().chunks_exact(channels.max(1) as usize * 4) {
+                for sample in frame.chunks_exact(4) {
+                    let sample = f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                    min = min.min(sample);
+                    max = max.max(sample);
+                }
+                frames += 1;
+                if frames >= block {
+                    peaks.push((min, max));
+                    (min, max, frames) = (f32::MAX, f32::MIN, 0);
+                }
+            }
+        }
+        if frames > 0 {
+            peaks.push((min, max));
+        }
+        Ok(peaks)
+    })();
+
+    let _ = pipeline.set_state(gst::State::Null);
+    peaks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn buckets_empty_peaks() {
+        assert_eq!(buckets(&[], 3), [(0.0, 0.0); 3]);
+        assert_eq!(buckets(&[(-1.0, 1.0)], 0), []);
+    }
+
+    #[test]
+    fn buckets_fewer_peaks() {
+        // every bucket spans at least one peak, so peaks are repeated
+        let peaks = [(-0.5, 0.5), (-1.0, 0.25)];
+        assert_eq!(
+            buckets(&peaks, 4),
+            [(-0.5, 0.5), (-0.5, 0.5), (-1.0, 0.25), (-1.0, 0.25)]
+        );
+    }
+
+    #[test]
+    fn buckets_uneven_division() {
+        let peaks = [
+            (-0.1, 0.1),
+            (-0.2, 0.2),
+            (-0.3, 0.3),
+            (-0.4, 0.4),
+            (-0.5, 0.5),
+        ];
+        // slices of 1, 2 and 2 peaks
+        assert_eq!(buckets(&peaks, 3), [(-0.1, 0.1), (-0.3, 0.3), (-0.5, 0.5)]);
+    }
+
+    #[test]
+    fn decodes_video_without_audio() {
+        gst::init().unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "iced_video_player_no_audio_{}.mkv",
+            std::process::id()
+        ));
+        let encode = format!(
+            "videotestsrc num-buffers=30 ! video/x-raw,format=I420,width=64,height=48,framerate=30/1 ! matroskamux ! filesink location=\"{}\"",
+            path.display()
+        );
+        let encoded = gst::parse::launch(&encode).ok().and_then(|pipeline| {
+            let bus = pipeline.bus()?;
+            pipeline.set_state(gst::State::Playing).ok()?;
+            let msg = bus.timed_pop_filtered(
+                gst::ClockTime::from_seconds(10),
+                &[gst::MessageType::Eos, gst::MessageType::Error],
+            );
+            let _ = pipeline.set_state(gst::State::Null);
+            matches!(msg?.view(), gst::MessageView::Eos(_)).then_some(())
+        });
+        if encoded.is_none() {
+            eprintln!("skipping waveform decoding: can't encode a test video");
+            return;
+        }
+
+        // decoding must finish, with an error or no peaks, rather than wait for audio forever
+        let uri = url::Url::from_file_path(&path).unwrap().to_string();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(decode_peaks(&uri, &AtomicBool::new(false)));
+        });
+        let result = receiver.recv_timeout(Duration::from_secs(30));
+        let _ = std::fs::remove_file(&path);
+
+        match result.expect("decoding a video without audio finishes") {
+            Ok(peaks) => assert!(peaks.is_empty(), "{peaks:?}"),
+            Err(err) => eprintln!("decoding failed as expected: {err}"),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Conversion of WebVTT cues into [`SubtitleCue`]s.
+//!
+//! The `line`, `position` and `align` cue settings are kept, along with `<b>`, `<i>`, `<u>` and the
+//! default color classes (e.g., `<c.yellow>`); regions, vertical text and ruby are not supported.
+
+use crate::subtitle::SubtitleCue;
+use iced::alignment::{Horizontal, Vertical};
+use std::time::Duration;
+
+/// Parses the cues of a whole file, ordered by start time.
+/// The header and `NOTE`, `STYLE` and `REGION` blocks are skipped.
+pub(crate) fn parse_file(text: &str) -> Vec<SubtitleCue> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues: Vec<_> = text
+        .split("\n\n")
+        // the timing line comes first or after the identifier
+        .filter(|block| {
+            block
+                .trim_start_matches('\n')
+                .lines()
+                .take(2)
+                .any(|line| line.contains("-->"))
+        })
+        .map(parse_cue)
+        .collect();
+
+    cues.sort_by_key(|cue| cue.start);
+    cues
+}
+
+/// Parses a cue block: an optional identifier, an optional timing line with settings, and the payload.
+pub(crate) fn parse_cue(block: &str) -> SubtitleCue {
+    let mut lines = block.trim_matches(['\r', '\n']).lines().peekable();
+
+    // the identifier precedes the timing line
+    let has_timing = |line: &&str| line.contains("-->");
+    if lines.peek().is_some_and(|line| !has_timing(line))
+        && lines.clone().nth(1).as_ref().is_some_and(has_timing)
+    {
+        lines.next();
+    }
+
+    let mut cue = SubtitleCue::default();
+    if let Some(timing) = lines.next_if(has_timing) {
+        let (start, rest) = timing.split_once("-->").unwrap();
+        let mut rest = rest.split_whitespace();
+        cue.start = parse_time(start.trim()).unwrap_or_default();
+        cue.end = rest.next().and_then(parse_time).unwrap_or_default();
+
+        for (key, value) in rest.filter_map(|setting| setting.split_once(':')) {
+            // settings may carry an alignment after a comma, e.g., `position:10%,line-left`
+            let value = value.split(',').next().unwrap_or_default();
+            match key {
+                "line" => {
+                    // line numbers depend on the line height, so only percentages are supported
+                    if let Some(percent) = parse_percent(value) {
+                        cue.y = Some(percent);
+                        cue.align_y = Vertical::Top;
+                    }
+                }
+                "position" => cue.x = parse_percent(value),
+                "align" => {
+                    cue.align_x = match value {
+                        "start" | "left" => Horizontal::Left,
+                        "end" | "right" => Horizontal::Right,
+                        _ => Horizontal::Center,
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    cue.markup = convert_payload(&lines.collect::<Vec<_>>().join("\n"));
+    cue
+}
+
+/// Converts the cue payload into Pango markup, which shares its entities and basic tags.
+fn convert_payload(payload: &str) -> String {
+    let mut markup = String::new();
+    // closing tags of the open elements, which may be unsupported and therefore empty
+    let mut open = Vec::new();
+
+    let mut rest = payload;
+    while let Some(start) = rest.find('<') {
+        markup.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            markup.push_str("&lt;");
+            rest = &rest[start + 1..];
+            continue;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        if tag.starts_with('/') {
+            if let Some(closing) = open.pop() {
+                markup.push_str(closing);
+            }
+            continue;
+        }
+
+        // the name is followed by classes (`c.yellow.bg_black`) or an annotation (`v Speaker`)
+        let name = tag.split(['.', ' ', '\t']).next().unwrap_or_default();
+        let closing = match name {
+            "b" | "i" | "u" => {
+                markup.push_str(&format!("<{name}>"));
+                match name {
+                    "b" => "</b>",
+                    "i" => "</i>",
+                    _ => "</u>",
+                }
+            }
+            "c" => match tag.split('.').skip(1).find_map(class_color) {
+                Some(color) => {
+                    markup.push_str(&format!("<span foreground=\"{color}\">"));
+                    "</span>"
+                }
+                None => "",
+            },
+            // timestamps inside the payload are not elements
+            _ if name.starts_with(|c: char| c.is_ascii_digit()) => continue,
+            _ => "",
+        };
+        open.push(closing);
+    }
+    markup.push_str(rest);
+
+    while let Some(closing) = open.pop() {
+        markup.push_str(closing);
+    }
+    markup
+}
+
+/// Maps the default text color classes to colors.
+fn class_color(class: &str) -> Option<&'static str> {
+    Some(match class {
+        "white" => "#ffffff",
+        "lime" => "#00ff00",
+        "cyan" => "#00ffff",
+        "red" => "#ff0000",
+        "yellow" => "#ffff00",
+        "magenta" => "#ff00ff",
+        "blue" => "#0000ff",
+        "black" => "#000000",
+        _ => return None,
+    })
+}
+
+fn parse_percent(value: &str) -> Option<f32> {
+    let percent: f32 = value.strip_suffix('%')?.parse().ok()?;
+    Some((percent / 100.0).clamp(0.0, 1.0))
+}
+
+/// Parses `[HH:]MM:SS.mmm`.
+fn parse_time(value: &str) -> Option<Duration> {
+    let (rest, seconds) = value.rsplit_once(':')?;
+    let minutes = match rest.rsplit_once(':') {
+        Some((hours, minutes)) => hours
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(60)?
+            .checked_add(minutes.parse().ok()?)?,
+        None => rest.parse().ok()?,
+    };
+    let seconds: f64 = seconds.parse().ok()?;
+    // rejects negative, infinite and NaN seconds
+    Duration::from_secs(minutes.checked_mul(60)?)
+        .checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(
+            parse_time("01:02:03.456"),
+            Some(Duration::from_millis(3_723_456))
+        );
+        assert_eq!(parse_time("02:03.5"), Some(Duration::from_millis(123_500)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for value in [
+            "",
+            "12.000",
+            "00:-1",
+            "00:inf",
+            "00:NaN",
+            "00:1e400",
+            "-1:00:00.000",
+            "00:00:00:00.000",
+            "18446744073709551615:00.000",
+            "18446744073709551615:00:00.000",
+        ] {
+            assert_eq!(parse_time(value), None, "{value}");
+        }
+        // cues with malformed timings mustn't take the file down with them
+        parse_file("WEBVTT\n\n00:-1.000 --> 00:inf\nBroken\n\n00:NaN --> 00:01.000\nBroken\n");
+    }
+
+    #[test]
+    fn parses_file() {
+        let cues = parse_file(
+            "\u{feff}WEBVTT - title\r\n\r\n\
+             NOTE skipped\r\n\r\n\
+             second\n00:00:05.000 --> 00:00:06.000 line:10% position:25%,line-left align:start\n\
+             <c.yellow>Second</c> &amp; <v Speaker>last</v>\n\n\
+             00:00:01.000 --> 00:00:02.500\n<b>First</b>\nline\n",
+        );
+        assert_eq!(cues.len(), 2);
+
+        assert_eq!(cues[0].start, Duration::from_secs(1));
+        assert_eq!(cues[0].end, Duration::from_millis(2500));
+        assert_eq!(cues[0].markup, "<b>First</b>\nline");
+
+        assert_eq!(cues[1].start, Duration::from_secs(5));
+        assert_eq!(
+            cues[1].markup,
+            "<span foreground=\"#ffff00\">Second</span> &amp; last"
+        );
+        assert_eq!(cues[1].y, Some(0.1));
+        assert_eq!(cues[1].align_y, Vertical::Top);
+        assert_eq!(cues[1].x, Some(0.25));
+        assert_eq!(cues[1].align_x, Horizontal::Left);
+    }
+}
@@ -0,0 +1,465 @@
+//! CPU conversion of decoded NV12 (or AV12, with alpha) frames into RGBA.
+//!
+//! The GPU path converts YUV to RGB in `shader.wgsl`; this is used wherever we need pixels on the CPU
+//! (thumbnails, software rendering). Software rendering uses the same BT.709 limited-range matrix as the
+//! shader so both paths produce the same colors, whereas thumbnails have always been BT.601.
+
+/// Limited-range YUV to RGB conversion matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Matrix {
+    Bt601,
+    /// Matches `fs_main` in shader.wgsl.
+    Bt709,
+}
+
+impl Matrix {
+    /// The `(R_V, G_U, G_V, B_U)` coefficients, applied to chroma centered on zero.
+    fn coefficients(self) -> [f32; 4] {
+        match self {
+            Matrix::Bt601 => [1.5960, 0.3918, 0.8130, 2.0172],
+            Matrix::Bt709 => [1.7927, 0.2132, 0.5329, 2.1124],
+        }
+    }
+}
+
+/// Resampling filter used when the destination size differs from the source size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Filter {
+    /// Picks the closest source pixel.
+    Nearest,
+    /// Interpolates between the four closest source pixels.
+    Bilinear,
+    /// Averages every source pixel covered by the destination pixel.
+    ///
+    /// Best suited for large downscales, where bilinear filtering would skip pixels and alias.
+    Box,
+}
+
+/// A borrowed NV12 frame.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Nv12<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    /// Byte offset of the alpha plane of an AV12 frame, which has the same layout as the Y plane.
+    alpha: Option<usize>,
+}
+
+impl<'a> Nv12<'a> {
+    /// `stride` is the line pitch of both planes in bytes; if unknown it is assumed to be equal to `width`.
+    pub fn new(data: &'a [u8], width: u32, height: u32, stride: Option<u32>) -> Self {
+        Nv12 {
+            data,
+            width,
+            height,
+            stride: stride.unwrap_or(width),
+            alpha: None,
+        }
+    }
+
+    /// Reads alpha from the plane starting `offset` bytes into the frame (AV12), instead of making
+    /// every pixel opaque.
+    pub fn with_alpha(self, offset: usize) -> Self {
+        Nv12 {
+            alpha: Some(offset),
+            ..self
+        }
+    }
+
+    /// Finds the picture area without black bars: the bounding box of the rows and columns where a
+    /// meaningful share of luma samples is brighter than `threshold`.
+    ///
+    /// Returns `None` if the whole frame is black.
+    pub fn active_area(&self, threshold: u8) -> Option<iced::Rectangle<u32>> {
+        let luma = self.luma();
+        let mut rows = vec![0u32; luma.height as usize];
+        let mut columns = vec![0u32; luma.width as usize];
+
+        for (y, count) in rows.iter_mut().enumerate() {
+            let line = &luma.data[y * luma.stride..][..luma.width as usize];
+            for (x, _) in line.iter().enumerate().filter(|(_, v)| **v > threshold) {
+                *count += 1;
+                columns[x] += 1;
+            }
+        }
+
+        // ignore isolated bright samples (noise, compression artifacts) inside the bars
+        let active = |counts: &[u32], length: u32| {
+            let min = length / 64;
+            let first = counts.iter().position(|&count| count > min)?;
+            let last = counts.iter().rposition(|&count| count > min)?;
+            Some((first as u32, (last - first) as u32 + 1))
+        };
+        let (x, width) = active(&columns, luma.height)?;
+        let (y, height) = active(&rows, luma.width)?;
+
+        Some(iced::Rectangle {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    fn luma(&self) -> Plane<'a> {
+        Plane {
+            data: self.data,
+            width: self.width,
+            height: self.height,
+            stride: self.stride as usize,
+            channels: 1,
+        }
+    }
+
+    fn chroma(&self) -> Plane<'a> {
+        Plane {
+            data: &self.data[(self.stride * self.height) as usize..],
+            width: self.width.div_ceil(2),
+            height: self.height.div_ceil(2),
+            stride: self.stride as usize,
+            channels: 2,
+        }
+    }
+
+    fn alpha(&self) -> Option<Plane<'a>> {
+        self.alpha.map(|offset| Plane {
+            data: &self.data[offset..],
+            ..self.luma()
+        })
+    }
+}
+
+/// Converts `frame` to tightly packed RGBA8 with `matrix`, resampled to `width` x `height` with `filter`.
+pub(crate) fn nv12_to_rgba(
+    frame: &Nv12<'_>,
+    width: u32,
+    height: u32,
+    filter: Filter,
+    matrix: Matrix,
+) -> Vec<u8> {
+    let mut rgba = vec![0; width as usize * height as usize * 4];
+    if width == 0 || height == 0 || frame.width == 0 || frame.height == 0 {
+        return rgba;
+    }
+
+    // no resampling needed, so skip the tap tables entirely
+    let filter = if (width, height) == (frame.width, frame.height) {
+        Filter::Nearest
+    } else {
+        filter
+    };
+
+    let luma = frame.luma();
+    let chroma = frame.chroma();
+    let alpha = frame.alpha();
+
+    let scale_x = frame.width as f32 / width as f32;
+    let scale_y = frame.height as f32 / height as f32;
+
+    // chroma is subsampled 2x in both directions, so its sample positions are halved
+    let luma_x = Taps::new(filter, scale_x, luma.width, width);
+    let luma_y = Taps::new(filter, scale_y, luma.height, height);
+    let chroma_x = Taps::new(filter, scale_x / 2.0, chroma.width, width);
+    let chroma_y = Taps::new(filter, scale_y / 2.0, chroma.height, height);
+
+    let mut y_row = vec![0.0; width as usize];
+    let mut u_row = vec![0.0; width as usize];
+    let mut v_row = vec![0.0; width as usize];
+    let mut a_row = vec![0.0; width as usize];
+
+    for (y, out) in rgba.chunks_exact_mut(width as usize * 4).enumerate() {
+        luma.sample_row(&luma_x, luma_y.get(y), 0, &mut y_row);
+        chroma.sample_row(&chroma_x, chroma_y.get(y), 0, &mut u_row);
+        chroma.sample_row(&chroma_x, chroma_y.get(y), 1, &mut v_row);
+        convert_row(matrix, &y_row, &u_row, &v_row, out);
+
+        if let Some(alpha) = &alpha {
+            alpha.sample_row(&luma_x, luma_y.get(y), 0, &mut a_row);
+            for (px, a) in out.chunks_exact_mut(4).zip(&a_row) {
+                px[3] = (a + 0.5).clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    rgba
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Plane<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+    channels: usize,
+}
+
+impl Plane<'_> {
+    /// Fills `out` with `channel` of one destination row, weighted by the vertical taps `rows`.
+    fn sample_row(&self, columns: &Taps, rows: (u32, &[f32]), channel: usize, out: &mut [f32]) {
+        let (first_row, row_weights) = rows;
+
+        if columns.single && row_weights.len() == 1 {
+            let line = &self.data[first_row as usize * self.stride..];
+            for (out, &x) in out.iter_mut().zip(&columns.starts) {
+                *out = line[x as usize * self.channels + channel] as f32;
+            }
+            return;
+        }
+
+        out.fill(0.0);
+        for (i, &row_weight) in row_weights.iter().enumerate() {
+            let line = &self.data[(first_row as usize + i) * self.stride..];
+            for (x, out) in out.iter_mut().enumerate() {
+                let (first_column, column_weights) = columns.get(x);
+                let mut sum = 0.0;
+                for (j, &column_weight) in column_weights.iter().enumerate() {
+                    sum += line[(first_column as usize + j) * self.channels + channel] as f32
+                        * column_weight;
+                }
+                *out += sum * row_weight;
+            }
+        }
+    }
+}
+
+/// Per-axis filter taps: for every destination pixel, the first source pixel and the weights of
+/// the consecutive source pixels that contribute to it.
+#[derive(Debug)]
+struct Taps {
+    starts: Vec<u32>,
+    ranges: Vec<(usize, usize)>,
+    weights: Vec<f32>,
+    /// Every destination pixel maps to exactly one source pixel.
+    single: bool,
+}
+
+impl Taps {
+    /// `scale` is the number of `src` pixels per destination pixel.
+    fn new(filter: Filter, scale: f32, src: u32, dst: u32) -> Self {
+        let mut taps = Taps {
+            starts: Vec::with_capacity(dst as usize),
+            ranges: Vec::with_capacity(dst as usize),
+            weights: Vec::new(),
+            single: filter == Filter::Nearest,
+        };
+        let last = src.saturating_sub(1);
+
+        for i in 0..dst {
+            let begin = taps.weights.len();
+            let center = (i as f32 + 0.5) * scale;
+            let start = match filter {
+                Filter::Nearest => {
+                    taps.weights.push(1.0);
+                    (center as u32).min(last)
+                }
+                Filter::Bilinear => {
+                    let pos = (center - 0.5).clamp(0.0, last as f32);
+                    let start = (pos as u32).min(last.saturating_sub(1));
+                    let frac = pos - start as f32;
+                    taps.weights.push(1.0 - frac);
+                    if start < last {
+                        taps.weights.push(frac);
+                    }
+                    start
+                }
+                Filter::Box => {
+                    // when upscaling the box is narrower than a source pixel; widen it to at least one pixel
+                    let half = scale.max(1.0) / 2.0;
+                    let lo = (center - half).max(0.0);
+                    let hi = (center + half).min(src as f32);
+                    let start = (lo as u32).min(last);
+                    let end = (hi.ceil() as u32).clamp(start + 1, src);
+                    for p in start..end {
+                        let coverage = (hi.min(p as f32 + 1.0) - lo.max(p as f32)).max(0.0);
+                        taps.weights.push(coverage / (hi - lo));
+                    }
+                    start
+                }
+            };
+            taps.starts.push(start);
+            taps.ranges.push((begin, taps.weights.len()));
+        }
+
+        taps
+    }
+
+    fn get(&self, i: usize) -> (u32, &[f32]) {
+        let (begin, end) = self.ranges[i];
+        (self.starts[i], &self.weights[begin..end])
+    }
+}
+
+// limited range, shared by both matrices
+const Y_OFFSET: f32 = 16.0;
+const UV_OFFSET: f32 = 128.0;
+const Y_SCALE: f32 = 1.1644;
+
+/// Converts one row of YUV samples to RGBA8, clamping out-of-gamut values.
+fn convert_row(matrix: Matrix, y: &[f32], u: &[f32], v: &[f32], out: &mut [u8]) {
+    let coefficients = matrix.coefficients();
+
+    #[cfg(target_arch = "x86_64")]
+    let done = {
+        // SSE2 is part of the x86_64 baseline, so no runtime detection is needed
+        // SAFETY: `convert_row_sse2` only reads `len / 4 * 4` elements of each input and writes as many pixels
+        unsafe { convert_row_sse2(coefficients, y, u, v, out) }
+    };
+    #[cfg(not(target_arch = "x86_64"))]
+    let done = 0;
+
+    for (i, px) in out.chunks_exact_mut(4).enumerate().skip(done) {
+        let (r, g, b) = yuv_to_rgb(coefficients, y[i], u[i], v[i]);
+        px[0] = (r + 0.5).clamp(0.0, 255.0) as u8;
+        px[1] = (g + 0.5).clamp(0.0, 255.0) as u8;
+        px[2] = (b + 0.5).clamp(0.0, 255.0) as u8;
+        px[3] = 0xFF;
+    }
+}
+
+#[inline(always)]
+fn yuv_to_rgb([r_v, g_u, g_v, b_u]: [f32; 4], y: f32, u: f32, v: f32) -> (f32, f32, f32) {
+    let y = (y - Y_OFFSET) * Y_SCALE;
+    let u = u - UV_OFFSET;
+    let v = v - UV_OFFSET;
+    (y + r_v * v, y - g_u * u - g_v * v, y + b_u * u)
+}
+
+/// Converts 4 pixels at a time, returning how many pixels were written.
+#[cfg(target_arch = "x86_64")]
+unsafe fn convert_row_sse2(
+    [r_v, g_u, g_v, b_u]: [f32; 4],
+    y: &[f32],
+    u: &[f32],
+    v: &[f32],
+    out: &mut [u8],
+) -> usize {
+    use std::arch::x86_64::*;
+
+    let len = y.len().min(u.len()).min(v.len()).min(out.len() / 4) / 4 * 4;
+
+    unsafe {
+        let y_offset = _mm_set1_ps(Y_OFFSET);
+        let uv_offset = _mm_set1_ps(UV_OFFSET);
+        let y_scale = _mm_set1_ps(Y_SCALE);
+        let r_v = _mm_set1_ps(r_v);
+        let g_u = _mm_set1_ps(g_u);
+        let g_v = _mm_set1_ps(g_v);
+        let b_u = _mm_set1_ps(b_u);
+        let alpha = _mm_set1_epi32(0xFF);
+
+        for i in (0..len).step_by(4) {
+            let yy = _mm_mul_ps(
+                _mm_sub_ps(_mm_loadu_ps(y.as_ptr().add(i)), y_offset),
+                y_scale,
+            );
+            let uu = _mm_sub_ps(_mm_loadu_ps(u.as_ptr().add(i)), uv_offset);
+            let vv = _mm_sub_ps(_mm_loadu_ps(v.as_ptr().add(i)), uv_offset);
+
+            let r = _mm_add_ps(yy, _mm_mul_ps(vv, r_v));
+            let g = _mm_sub_ps(_mm_sub_ps(yy, _mm_mul_ps(uu, g_u)), _mm_mul_ps(vv, g_v));
+            let b = _mm_add_ps(yy, _mm_mul_ps(uu, b_u));
+
+            // the saturating packs take care of clamping to 0..=255
+            let rg = _mm_packs_epi32(_mm_cvtps_epi32(r), _mm_cvtps_epi32(g));
+            let ba = _mm_packs_epi32(_mm_cvtps_epi32(b), alpha);
+            // RRRR GGGG BBBB AAAA
+            let planar = _mm_packus_epi16(rg, ba);
+
+            // transpose to RGBA RGBA RGBA RGBA
+            let rg = _mm_unpacklo_epi8(planar, _mm_srli_si128::<4>(planar));
+            let ba = _mm_unpacklo_epi8(_mm_srli_si128::<8>(planar), _mm_srli_si128::<12>(planar));
+            let rgba = _mm_unpacklo_epi16(rg, ba);
+
+            _mm_storeu_si128(out.as_mut_ptr().add(i * 4) as *mut __m128i, rgba);
+        }
+    }
+
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converts a pixel the way the scalar tail of `convert_row` does.
+    fn scalar(matrix: Matrix, y: f32, u: f32, v: f32) -> [u8; 4] {
+        let (r, g, b) = yuv_to_rgb(matrix.coefficients(), y, u, v);
+        let channel = |c: f32| (c + 0.5).clamp(0.0, 255.0) as u8;
+        [channel(r), channel(g), channel(b), 0xFF]
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        // deterministic samples covering the full range, out-of-gamut combinations included
+        let mut seed = 0x2545_f491_u32;
+        let mut next = || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 24) as f32
+        };
+        // not a multiple of 4, so the scalar tail runs too
+        let len = 1027;
+        let y: Vec<f32> = (0..len).map(|_| next()).collect();
+        let u: Vec<f32> = (0..len).map(|_| next()).collect();
+        let v: Vec<f32> = (0..len).map(|_| next()).collect();
+
+        for matrix in [Matrix::Bt601, Matrix::Bt709] {
+            let mut out = vec![0; len * 4];
+            convert_row(matrix, &y, &u, &v, &mut out);
+
+            for (i, px) in out.chunks_exact(4).enumerate() {
+                let expected = scalar(matrix, y[i], u[i], v[i]);
+                // SSE2 rounds halves to even, the scalar path rounds them up
+                for (a, b) in px.iter().zip(expected) {
+                    assert!(a.abs_diff(b) <= 1, "pixel {i}: {px:?} != {expected:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn converts_limited_range() {
+        // 5x2 frame: black, white and gray columns with neutral chroma
+        let (width, height) = (5, 2);
+        let mut data = vec![128; 10 + 6];
+        data[..10].copy_from_slice(&[16, 235, 126, 16, 235, 16, 235, 126, 16, 235]);
+        let frame = Nv12::new(&data, width, height, None);
+
+        let rgba = nv12_to_rgba(&frame, width, height, Filter::Bilinear, Matrix::Bt709);
+        let pixels: Vec<_> = rgba.chunks_exact(4).take(5).collect();
+        assert_eq!(pixels[0], [0, 0, 0, 255]);
+        assert_eq!(pixels[1], [255, 255, 255, 255]);
+        assert_eq!(pixels[2], [128, 128, 128, 255]);
+        assert_eq!(pixels[4], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn keeps_bt601_for_thumbnails() {
+        // 2x2 frame of pure red in BT.601
+        let data = [81, 81, 81, 81, 90, 240];
+        let frame = Nv12::new(&data, 2, 2, None);
+
+        let bt601 = nv12_to_rgba(&frame, 2, 2, Filter::Nearest, Matrix::Bt601);
+        assert!(
+            bt601
+                .chunks_exact(4)
+                .all(|px| px[0] >= 250 && px[1..] == [0, 0, 255]),
+            "{bt601:?}"
+        );
+        // BT.709 tints it
+        let bt709 = nv12_to_rgba(&frame, 2, 2, Filter::Nearest, Matrix::Bt709);
+        assert!(bt709[1] > 20, "{bt709:?}");
+    }
+
+    #[test]
+    fn reads_alpha_plane() {
+        let (width, height) = (2, 2);
+        // Y, interleaved UV, then alpha laid out like Y
+        let data = [16, 16, 16, 16, 128, 128, 0, 255, 64, 128];
+        let frame = Nv12::new(&data, width, height, None).with_alpha(6);
+
+        let rgba = nv12_to_rgba(&frame, width, height, Filter::Nearest, Matrix::Bt709);
+        let alpha: Vec<_> = rgba.chunks_exact(4).map(|px| px[3]).collect();
+        assert_eq!(alpha, [0, 255, 64, 128]);
+    }
+}